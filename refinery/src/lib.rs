@@ -32,9 +32,14 @@ for more examples refer to the [examples](https://github.com/rust-db/refinery/tr
 */
 
 pub use refinery_core::config;
+pub use refinery_core::schema;
 pub use refinery_core::{
-    error, load_sql_migrations, Error, Migration, Report, Runner, SchemaVersion, Target,
+    error, load_migration_directory, load_sql_migrations, load_sql_migrations_with_naming, Error,
+    Migration, MigrationMetadata, NamingStrategy, Report, Runner, SchemaVersion, Target,
 };
 #[doc(hidden)]
 pub use refinery_core::{AsyncMigrate, Migrate};
 pub use refinery_macros::embed_migrations;
+
+#[cfg(feature = "rusqlite-async")]
+pub use refinery_core::AsyncRusqliteConnection;
@@ -0,0 +1,86 @@
+#[cfg(feature = "diesel-sqlite")]
+mod diesel {
+    use refinery::{Migrate, Migration, Runner};
+    use refinery_core::diesel::{Connection, SqliteConnection};
+    #[cfg(feature = "rusqlite")]
+    use tempfile::TempDir;
+
+    const DEFAULT_TABLE_NAME: &str = "refinery_schema_history";
+
+    fn get_migrations() -> Vec<Migration> {
+        let migration1 = Migration::unapplied(
+            "1V__initial",
+            "CREATE TABLE persons (id int, name varchar(255), city varchar(255));",
+        )
+        .unwrap();
+
+        let migration2 = Migration::unapplied(
+            "2V__add_cars_table",
+            "CREATE TABLE cars (id int, name varchar(255));",
+        )
+        .unwrap();
+
+        let migration3 = Migration::unapplied(
+            "3V__add_brand_to_cars_table",
+            "ALTER TABLE cars ADD brand varchar(255);",
+        )
+        .unwrap();
+
+        vec![migration1, migration2, migration3]
+    }
+
+    fn assert_applied(applied: &[Migration], migrations: &[Migration]) {
+        assert_eq!(migrations.len(), applied.len());
+        for (applied, migration) in applied.iter().zip(migrations) {
+            assert_eq!(migration.version(), applied.version());
+            assert_eq!(migration.name(), applied.name());
+            assert_eq!(migration.checksum(), applied.checksum());
+            assert!(applied.applied_on().is_some());
+        }
+    }
+
+    #[test]
+    fn migrates_connection() {
+        let mut conn = SqliteConnection::establish(":memory:").unwrap();
+        let migrations = get_migrations();
+
+        let report = Runner::new(&migrations).run(&mut conn).unwrap();
+        assert_applied(report.applied_migrations(), &migrations);
+
+        let report = Runner::new(&migrations).run(&mut conn).unwrap();
+        assert!(report.applied_migrations().is_empty());
+
+        let applied = conn.get_applied_migrations(DEFAULT_TABLE_NAME).unwrap();
+        assert_applied(&applied, &migrations);
+        let last = conn
+            .get_last_applied_migration(DEFAULT_TABLE_NAME)
+            .unwrap()
+            .unwrap();
+        assert_eq!(3, last.version());
+    }
+
+    // diesel shares the migrations history table with the other drivers
+    #[test]
+    #[cfg(feature = "rusqlite")]
+    fn reads_history_written_by_rusqlite() {
+        use refinery_core::rusqlite;
+
+        let dir = TempDir::new().unwrap();
+        let db_path = dir.path().join("db.sql");
+        let migrations = get_migrations();
+
+        let mut rusqlite_conn = rusqlite::Connection::open(&db_path).unwrap();
+        Runner::new(&migrations[..2])
+            .run(&mut rusqlite_conn)
+            .unwrap();
+
+        let mut conn = SqliteConnection::establish(db_path.to_str().unwrap()).unwrap();
+        let report = Runner::new(&migrations).run(&mut conn).unwrap();
+        assert_applied(report.applied_migrations(), &migrations[2..]);
+
+        let applied = rusqlite_conn
+            .get_applied_migrations(DEFAULT_TABLE_NAME)
+            .unwrap();
+        assert_applied(&applied, &migrations);
+    }
+}
@@ -36,7 +36,7 @@ mod mysql {
         embed_migrations!("./tests/migrations");
 
         let migration1 =
-            Migration::unapplied("1V__initial.rs", &migrations::1V__initial::migration()).unwrap();
+            Migration::unapplied("1V__initial.rs", &migrations::_1V__initial::migration()).unwrap();
 
         let migration2 = Migration::unapplied(
             "2V__add_cars_and_motos_table.sql",
@@ -52,7 +52,7 @@ mod mysql {
 
         let migration4 = Migration::unapplied(
             "4V__add_year_to_motos_table.rs",
-            &migrations::4V__add_year_to_motos_table::migration(),
+            &migrations::_4V__add_year_to_motos_table::migration(),
         )
         .unwrap();
 
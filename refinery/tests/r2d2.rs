@@ -0,0 +1,106 @@
+#[cfg(all(
+    feature = "r2d2",
+    any(feature = "rusqlite", feature = "rusqlite-bundled")
+))]
+mod r2d2 {
+    use refinery::{Migrate, Migration, Runner};
+    use refinery_core::r2d2::{ManageConnection, Pool};
+    use refinery_core::rusqlite::{Connection, Error};
+    use std::path::PathBuf;
+    use tempfile::TempDir;
+
+    const DEFAULT_TABLE_NAME: &str = "refinery_schema_history";
+
+    // Connections to the same database file, so that they all see the migrated schema
+    struct SqliteConnectionManager(PathBuf);
+
+    impl ManageConnection for SqliteConnectionManager {
+        type Connection = Connection;
+        type Error = Error;
+
+        fn connect(&self) -> Result<Connection, Error> {
+            Connection::open(&self.0)
+        }
+
+        fn is_valid(&self, conn: &mut Connection) -> Result<(), Error> {
+            conn.execute_batch("")
+        }
+
+        fn has_broken(&self, _conn: &mut Connection) -> bool {
+            false
+        }
+    }
+
+    fn get_pool(dir: &TempDir) -> Pool<SqliteConnectionManager> {
+        Pool::builder()
+            .max_size(2)
+            .build(SqliteConnectionManager(dir.path().join("db.sql")))
+            .unwrap()
+    }
+
+    fn get_migrations() -> Vec<Migration> {
+        let migration1 = Migration::unapplied(
+            "1V__initial",
+            "CREATE TABLE persons (id int, name varchar(255), city varchar(255));",
+        )
+        .unwrap();
+
+        let migration2 = Migration::unapplied(
+            "2V__add_cars_table",
+            "CREATE TABLE cars (id int, name varchar(255));",
+        )
+        .unwrap();
+
+        let migration3 = Migration::unapplied(
+            "3V__add_brand_to_cars_table",
+            "ALTER TABLE cars ADD brand varchar(255);",
+        )
+        .unwrap();
+
+        vec![migration1, migration2, migration3]
+    }
+
+    fn assert_applied(applied: &[Migration], migrations: &[Migration]) {
+        assert_eq!(migrations.len(), applied.len());
+        for (applied, migration) in applied.iter().zip(migrations) {
+            assert_eq!(migration.version(), applied.version());
+            assert_eq!(migration.name(), applied.name());
+            assert_eq!(migration.checksum(), applied.checksum());
+        }
+    }
+
+    #[test]
+    fn migrates_pool() {
+        let dir = TempDir::new().unwrap();
+        let mut pool = get_pool(&dir);
+        let migrations = get_migrations();
+
+        let report = Runner::new(&migrations).run(&mut pool).unwrap();
+        assert_applied(report.applied_migrations(), &migrations);
+
+        let report = Runner::new(&migrations).run(&mut pool).unwrap();
+        assert!(report.applied_migrations().is_empty());
+
+        let applied = pool.get_applied_migrations(DEFAULT_TABLE_NAME).unwrap();
+        assert_applied(&applied, &migrations);
+    }
+
+    #[test]
+    fn migrates_pooled_connection() {
+        let dir = TempDir::new().unwrap();
+        let pool = get_pool(&dir);
+        let migrations = get_migrations();
+
+        let mut conn = pool.get().unwrap();
+        let report = Runner::new(&migrations).run(&mut conn).unwrap();
+        assert_applied(report.applied_migrations(), &migrations);
+
+        // another connection of the pool sees the migrations applied
+        let mut conn = pool.get().unwrap();
+        let report = Runner::new(&migrations).run(&mut conn).unwrap();
+        assert!(report.applied_migrations().is_empty());
+
+        let applied = conn.get_applied_migrations(DEFAULT_TABLE_NAME).unwrap();
+        assert_applied(&applied, &migrations);
+    }
+}
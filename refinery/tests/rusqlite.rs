@@ -5,10 +5,10 @@ mod rusqlite {
     use assert_cmd::prelude::*;
     use predicates::str::contains;
     use refinery::{
-        config::{Config, ConfigDbType},
+        config::{Config, ConfigDbType, MigrationsConfig},
         embed_migrations,
         error::Kind,
-        Migrate, Migration, Runner, Target,
+        Migrate, Migration, MigrationMetadata, Runner, Target,
     };
     use refinery_core::rusqlite::Error;
     use refinery_core::rusqlite::{Connection, OptionalExtension};
@@ -51,7 +51,7 @@ mod rusqlite {
         embed_migrations!("./tests/migrations");
 
         let migration1 =
-            Migration::unapplied("1V__initial.rs", &migrations::1V__initial::migration()).unwrap();
+            Migration::unapplied("1V__initial.rs", &migrations::_1V__initial::migration()).unwrap();
 
         let migration2 = Migration::unapplied(
             "2V__add_cars_and_motos_table.sql",
@@ -67,7 +67,7 @@ mod rusqlite {
 
         let migration4 = Migration::unapplied(
             "4V__add_year_to_motos_table.rs",
-            &migrations::4V__add_year_to_motos_table::migration(),
+            &migrations::_4V__add_year_to_motos_table::migration(),
         )
         .unwrap();
 
@@ -373,7 +373,7 @@ mod rusqlite {
             current.applied_on().unwrap().date()
         );
         assert_eq!(2, current.version());
-        assert!(err.report().unwrap().applied_migrations().is_empty());
+        assert!(err.report().is_none());
         assert_eq!(2, applied_migrations.len());
 
         assert_eq!(1, applied_migrations[0].version());
@@ -404,6 +404,70 @@ mod rusqlite {
         assert!(query.is_none());
     }
 
+    #[test]
+    fn locates_failing_statement_if_not_grouped() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        let migration = Migration::unapplied(
+            "1V__broken",
+            "CREATE TABLE persons (name TEXT);
+INSERT INTO persons (name) VALUES ('a');
+INSERT INTO cars (name) VALUES ('b');",
+        )
+        .unwrap();
+
+        let err = Runner::new(&[migration]).run(&mut conn).unwrap_err();
+
+        let position = err.position().unwrap();
+        assert_eq!(3, position.statement);
+        assert_eq!(3, position.line);
+        assert_eq!(None, position.column);
+        assert!(err.native_code().unwrap().number.is_some());
+    }
+
+    #[test]
+    fn applies_non_transactional_migration_outside_transaction() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        // sqlite can't VACUUM within a transaction
+        let vacuum = Migration::unapplied("1V__vacuum", "VACUUM;")
+            .unwrap()
+            .set_metadata(MigrationMetadata {
+                transactional: false,
+                ..MigrationMetadata::default()
+            });
+
+        let report = Runner::new(&[vacuum]).run(&mut conn).unwrap();
+
+        assert_eq!(1, report.applied_migrations().len());
+        let current = conn
+            .get_last_applied_migration(DEFAULT_TABLE_NAME)
+            .unwrap()
+            .unwrap();
+        assert_eq!(1, current.version());
+    }
+
+    #[test]
+    fn aborts_on_non_transactional_migration_if_grouped() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        let vacuum = Migration::unapplied("1V__vacuum", "VACUUM;")
+            .unwrap()
+            .set_metadata(MigrationMetadata {
+                transactional: false,
+                ..MigrationMetadata::default()
+            });
+
+        let err = Runner::new(&[vacuum])
+            .set_grouped(true)
+            .run(&mut conn)
+            .unwrap_err();
+
+        match err.kind() {
+            Kind::NonTransactionalGrouped(migration) => {
+                assert_eq!("vacuum", migration.name());
+            }
+            _ => panic!("failed test"),
+        }
+    }
+
     #[test]
     fn gets_applied_migrations() {
         let mut conn = Connection::open_in_memory().unwrap();
@@ -666,6 +730,103 @@ mod rusqlite {
         }
     }
 
+    #[test]
+    fn squashes_migrations_into_baseline() {
+        let mut conn = Connection::open_in_memory().unwrap();
+
+        let baseline = embedded::migrations::runner()
+            .squash(&mut conn, 2)
+            .unwrap();
+
+        assert_eq!("2B__baseline", baseline.to_string());
+        assert!(baseline.sql().unwrap().contains("cars"));
+    }
+
+    #[test]
+    fn squash_fails_on_non_empty_database() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        embedded::migrations::runner().run(&mut conn).unwrap();
+
+        let err = embedded::migrations::runner()
+            .squash(&mut conn, 2)
+            .unwrap_err();
+
+        assert!(matches!(err.kind(), Kind::NonEmptyScratchDatabase));
+    }
+
+    #[test]
+    fn baseline_creates_the_schema_of_the_squashed_migrations() {
+        let mut scratch = Connection::open_in_memory().unwrap();
+        let baseline = embedded::migrations::runner()
+            .squash(&mut scratch, 2)
+            .unwrap();
+
+        let mut migrated = Connection::open_in_memory().unwrap();
+        embedded::migrations::runner()
+            .set_target(Target::Version(2))
+            .run(&mut migrated)
+            .unwrap();
+        let mut baselined = Connection::open_in_memory().unwrap();
+        Runner::new(&[baseline]).run(&mut baselined).unwrap();
+
+        let schema = migrated.introspect_schema(DEFAULT_TABLE_NAME).unwrap();
+        assert!(schema.table("cars").is_some());
+        assert_eq!(
+            schema,
+            baselined.introspect_schema(DEFAULT_TABLE_NAME).unwrap()
+        );
+    }
+
+    #[test]
+    fn squash_fails_on_objects_the_baseline_cant_hold() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        let migration = Migration::unapplied(
+            "1V__initial",
+            "CREATE TABLE persons (id INTEGER PRIMARY KEY, name TEXT CHECK (name <> ''));
+CREATE TABLE cars (id INTEGER PRIMARY KEY, owner_id INTEGER REFERENCES persons (id));
+CREATE INDEX cars_abs_owner ON cars (abs(owner_id));
+CREATE VIEW owners AS SELECT name FROM persons;",
+        )
+        .unwrap();
+
+        let err = Runner::new(&[migration]).squash(&mut conn, 1).unwrap_err();
+
+        match err.kind() {
+            Kind::UncapturedSchemaObjects(objects) => assert_eq!(
+                &vec![
+                    "view owners".to_string(),
+                    "foreign key cars.owner_id".to_string(),
+                    "check constraint persons".to_string(),
+                    "index cars_abs_owner".to_string(),
+                ],
+                objects
+            ),
+            _ => panic!("failed test"),
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "toml")]
+    fn detect_drift_fails_without_snapshot_and_doesnt_create_its_table() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        embedded::migrations::runner().run(&mut conn).unwrap();
+
+        let err = embedded::migrations::runner()
+            .detect_drift(&mut conn)
+            .unwrap_err();
+
+        assert!(matches!(err.kind(), Kind::MissingSnapshot));
+        let snapshot_table: Option<String> = conn
+            .query_row(
+                "SELECT name FROM sqlite_master WHERE name = 'refinery_schema_history_snapshot'",
+                [],
+                |row| row.get(0),
+            )
+            .optional()
+            .unwrap();
+        assert!(snapshot_table.is_none());
+    }
+
     #[test]
     fn migrates_from_config() {
         let db = tempfile::NamedTempFile::new_in(".").unwrap();
@@ -701,6 +862,67 @@ mod rusqlite {
         assert_eq!(migrations[4].checksum(), applied_migrations[4].checksum());
     }
 
+    #[test]
+    fn migrates_from_config_migrations_settings() {
+        let db = tempfile::NamedTempFile::new_in(".").unwrap();
+        let mut config = Config::new(ConfigDbType::Sqlite)
+            .set_db_path(db.path().to_str().unwrap())
+            .set_migrations(MigrationsConfig {
+                table_name: Some("custom_schema_history".into()),
+                target: Some(3),
+                ..MigrationsConfig::default()
+            });
+
+        let migrations = get_migrations();
+        let runner = Runner::from_config(&config, &migrations);
+        let report = runner.run(&mut config).unwrap();
+        assert_eq!(3, report.applied_migrations().len());
+
+        let mut conn = Connection::open(db.path()).unwrap();
+        let current = conn
+            .get_last_applied_migration("custom_schema_history")
+            .unwrap()
+            .unwrap();
+        assert_eq!(3, current.version());
+    }
+
+    #[test]
+    fn migrates_from_config_creating_missing_database() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = dir.path().join("new.db");
+        let mut config = Config::new(ConfigDbType::Sqlite)
+            .set_db_path(db.to_str().unwrap())
+            .set_create_if_missing(true)
+            .set_pragmas(&["journal_mode=WAL", "foreign_keys=ON"]);
+
+        let migrations = get_migrations();
+        let report = Runner::new(&migrations).run(&mut config).unwrap();
+        assert_eq!(5, report.applied_migrations().len());
+
+        let conn = Connection::open(&db).unwrap();
+        let journal_mode: String = conn
+            .query_row("PRAGMA journal_mode", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!("wal", journal_mode);
+    }
+
+    #[test]
+    fn migrates_in_memory_database_from_config_only_once() {
+        let mut config = Config::new(ConfigDbType::Sqlite).set_db_path(":memory:");
+
+        let migrations = get_migrations();
+        let runner = Runner::new(&migrations);
+        let report = runner.run(&mut config).unwrap();
+        assert_eq!(5, report.applied_migrations().len());
+
+        // a new connection would open a new, empty, database
+        let err = runner.get_last_applied_migration(&mut config).unwrap_err();
+        assert!(matches!(err.kind(), Kind::ConfigError(_)));
+        let mut config = Config::new(ConfigDbType::Sqlite).set_db_path("file:app?mode=memory");
+        let err = runner.get_applied_migrations(&mut config).unwrap_err();
+        assert!(matches!(err.kind(), Kind::ConfigError(_)));
+    }
+
     #[test]
     fn migrate_from_config_report_contains_migrations() {
         let db = tempfile::NamedTempFile::new_in(".").unwrap();
@@ -0,0 +1,87 @@
+#[cfg(feature = "rusqlite-async")]
+mod rusqlite_async {
+    use refinery::config::{Config, ConfigDbType};
+    use refinery::{AsyncMigrate, AsyncRusqliteConnection, Migration, Runner};
+    use refinery_core::rusqlite::Connection;
+    use tempfile::TempDir;
+
+    const DEFAULT_TABLE_NAME: &str = "refinery_schema_history";
+
+    fn get_migrations() -> Vec<Migration> {
+        let migration1 = Migration::unapplied(
+            "1V__initial",
+            "CREATE TABLE persons (id int, name varchar(255), city varchar(255));",
+        )
+        .unwrap();
+
+        let migration2 = Migration::unapplied(
+            "2V__add_cars_table",
+            "CREATE TABLE cars (id int, name varchar(255));",
+        )
+        .unwrap();
+
+        let migration3 = Migration::unapplied(
+            "3V__add_brand_to_cars_table",
+            "ALTER TABLE cars ADD brand varchar(255);",
+        )
+        .unwrap();
+
+        vec![migration1, migration2, migration3]
+    }
+
+    fn assert_applied(applied: &[Migration], migrations: &[Migration]) {
+        assert_eq!(migrations.len(), applied.len());
+        for (applied, migration) in applied.iter().zip(migrations) {
+            assert_eq!(migration.version(), applied.version());
+            assert_eq!(migration.name(), applied.name());
+            assert_eq!(migration.checksum(), applied.checksum());
+        }
+    }
+
+    #[tokio::test]
+    async fn migrates_connection() {
+        let mut conn = AsyncRusqliteConnection::new(Connection::open_in_memory().unwrap());
+        let migrations = get_migrations();
+
+        let report = Runner::new(&migrations).run_async(&mut conn).await.unwrap();
+        assert_applied(report.applied_migrations(), &migrations);
+
+        let report = Runner::new(&migrations).run_async(&mut conn).await.unwrap();
+        assert!(report.applied_migrations().is_empty());
+
+        let applied = conn
+            .get_applied_migrations(DEFAULT_TABLE_NAME)
+            .await
+            .unwrap();
+        assert_applied(&applied, &migrations);
+    }
+
+    // Config opens an AsyncRusqliteConnection for Sqlite, unless the sqlx feature is enabled too
+    #[tokio::test]
+    async fn migrates_from_config() {
+        let dir = TempDir::new().unwrap();
+        let db_path = dir.path().join("db.sql");
+        let mut config = Config::new(ConfigDbType::Sqlite)
+            .set_db_path(db_path.to_str().unwrap())
+            .set_create_if_missing(true);
+        let migrations = get_migrations();
+
+        let report = Runner::new(&migrations)
+            .run_async(&mut config)
+            .await
+            .unwrap();
+        assert_applied(report.applied_migrations(), &migrations);
+
+        let report = Runner::new(&migrations)
+            .run_async(&mut config)
+            .await
+            .unwrap();
+        assert!(report.applied_migrations().is_empty());
+
+        let applied = config
+            .get_applied_migrations(DEFAULT_TABLE_NAME)
+            .await
+            .unwrap();
+        assert_applied(&applied, &migrations);
+    }
+}
@@ -0,0 +1,88 @@
+#[cfg(feature = "sqlx")]
+mod sqlx {
+    use refinery::{AsyncMigrate, Migration, Runner};
+    use refinery_core::sqlx::sqlite::{SqliteConnectOptions, SqliteConnection, SqlitePool};
+    use refinery_core::sqlx::Connection;
+    use tempfile::TempDir;
+
+    const DEFAULT_TABLE_NAME: &str = "refinery_schema_history";
+
+    fn connect_options(dir: &TempDir) -> SqliteConnectOptions {
+        SqliteConnectOptions::new()
+            .filename(dir.path().join("db.sql"))
+            .create_if_missing(true)
+    }
+
+    fn get_migrations() -> Vec<Migration> {
+        let migration1 = Migration::unapplied(
+            "1V__initial",
+            "CREATE TABLE persons (id int, name varchar(255), city varchar(255));",
+        )
+        .unwrap();
+
+        let migration2 = Migration::unapplied(
+            "2V__add_cars_table",
+            "CREATE TABLE cars (id int, name varchar(255));",
+        )
+        .unwrap();
+
+        let migration3 = Migration::unapplied(
+            "3V__add_brand_to_cars_table",
+            "ALTER TABLE cars ADD brand varchar(255);",
+        )
+        .unwrap();
+
+        vec![migration1, migration2, migration3]
+    }
+
+    fn assert_applied(applied: &[Migration], migrations: &[Migration]) {
+        assert_eq!(migrations.len(), applied.len());
+        for (applied, migration) in applied.iter().zip(migrations) {
+            assert_eq!(migration.version(), applied.version());
+            assert_eq!(migration.name(), applied.name());
+            assert_eq!(migration.checksum(), applied.checksum());
+        }
+    }
+
+    #[tokio::test]
+    async fn migrates_pool() {
+        let dir = TempDir::new().unwrap();
+        let mut pool = SqlitePool::connect_with(connect_options(&dir))
+            .await
+            .unwrap();
+        let migrations = get_migrations();
+
+        let report = Runner::new(&migrations).run_async(&mut pool).await.unwrap();
+        assert_applied(report.applied_migrations(), &migrations);
+
+        let report = Runner::new(&migrations).run_async(&mut pool).await.unwrap();
+        assert!(report.applied_migrations().is_empty());
+
+        let applied = pool
+            .get_applied_migrations(DEFAULT_TABLE_NAME)
+            .await
+            .unwrap();
+        assert_applied(&applied, &migrations);
+    }
+
+    #[tokio::test]
+    async fn migrates_connection() {
+        let dir = TempDir::new().unwrap();
+        let mut conn = SqliteConnection::connect_with(&connect_options(&dir))
+            .await
+            .unwrap();
+        let migrations = get_migrations();
+
+        let report = Runner::new(&migrations).run_async(&mut conn).await.unwrap();
+        assert_applied(report.applied_migrations(), &migrations);
+
+        let report = Runner::new(&migrations).run_async(&mut conn).await.unwrap();
+        assert!(report.applied_migrations().is_empty());
+
+        let applied = conn
+            .get_applied_migrations(DEFAULT_TABLE_NAME)
+            .await
+            .unwrap();
+        assert_applied(&applied, &migrations);
+    }
+}
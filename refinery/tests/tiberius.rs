@@ -23,7 +23,7 @@ mod tiberius {
         embed_migrations!("./tests/migrations");
 
         let migration1 =
-            Migration::unapplied("1V__initial.rs", &migrations::1V__initial::migration()).unwrap();
+            Migration::unapplied("1V__initial.rs", &migrations::_1V__initial::migration()).unwrap();
 
         let migration2 = Migration::unapplied(
             "2V__add_cars_and_motos_table.sql",
@@ -39,7 +39,7 @@ mod tiberius {
 
         let migration4 = Migration::unapplied(
             "4V__add_year_to_motos_table.rs",
-            &migrations::4V__add_year_to_motos_table::migration(),
+            &migrations::_4V__add_year_to_motos_table::migration(),
         )
         .unwrap();
 
@@ -487,6 +487,64 @@ mod tiberius {
         .await
     }
 
+    #[tokio::test]
+    async fn introspects_precision_and_scale() {
+        run_test(async {
+            let config = Config::from_str(CONFIG).unwrap();
+
+            let tcp = tokio::net::TcpStream::connect(format!(
+                "{}:{}",
+                config.db_host().unwrap(),
+                config.db_port().unwrap()
+            ))
+            .await
+            .unwrap();
+            let mut tconfig: TConfig = (&config).try_into().unwrap();
+            tconfig.trust_cert();
+            let mut client = tiberius::Client::connect(tconfig, tcp.compat_write())
+                .await
+                .unwrap();
+
+            let migration = Migration::unapplied(
+                "1V__prices",
+                "CREATE TABLE prices (amount decimal(10, 2), rate numeric(5, 4), created_at datetime2(3), \
+                 opens_at time(0), closes_at datetimeoffset(7));",
+            )
+            .unwrap();
+            Runner::new(&[migration])
+                .run_async(&mut client)
+                .await
+                .unwrap();
+
+            let schema = client
+                .introspect_schema(DEFAULT_TABLE_NAME)
+                .await
+                .unwrap();
+            let prices = schema
+                .tables
+                .iter()
+                .find(|table| table.name == "prices")
+                .unwrap();
+            let data_types = prices
+                .columns
+                .iter()
+                .map(|column| column.data_type.as_str())
+                .collect::<Vec<_>>();
+
+            assert_eq!(
+                vec![
+                    "decimal(10,2)",
+                    "numeric(5,4)",
+                    "datetime2(3)",
+                    "time(0)",
+                    "datetimeoffset(7)"
+                ],
+                data_types
+            );
+        })
+        .await
+    }
+
     #[tokio::test]
     async fn applies_new_migration() {
         run_test(async {
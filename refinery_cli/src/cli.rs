@@ -4,7 +4,7 @@ use std::path::PathBuf;
 
 use clap::{Args, Parser};
 
-use refinery_core::SchemaVersion;
+use refinery_core::{config::ConfigDbType, SchemaVersion};
 
 #[derive(Parser)]
 #[clap(version)]
@@ -14,6 +14,12 @@ pub enum Cli {
 
     /// Refinery's main migrate operation
     Migrate(MigrateArgs),
+
+    /// Squash the migrations up to a version into a baseline migration
+    Squash(SquashArgs),
+
+    /// Report schema changes made since the snapshot recorded after the last migration
+    Drift(DriftArgs),
 }
 
 #[derive(Args)]
@@ -22,17 +28,30 @@ pub struct MigrateArgs {
     #[clap(short, default_value = "./refinery.toml")]
     pub config: PathBuf,
 
-    /// Migrations directory path
-    #[clap(short, default_value = "./migrations")]
-    pub path: PathBuf,
+    /// Migrations directory path [default: the path of the config file's [migrations] section, or ./migrations]
+    #[clap(short)]
+    pub path: Option<PathBuf>,
 
     /// Load database from the given environment variable
     #[clap(short)]
     pub env_var: Option<String>,
 
-    /// Run migrations grouped in a single transaction
-    #[clap(short)]
-    pub grouped: bool,
+    /// Connect to a database of this type (postgres or mysql) configured only by the libpq or MySQL
+    /// client environment variables and files, i.e. PGHOST or ~/.my.cnf, instead of a config file
+    #[clap(long)]
+    pub db_type: Option<ConfigDbType>,
+
+    /// Use the given [environments.<name>] section of the config file, overriding its [main] section
+    #[clap(long, conflicts_with_all = ["env_var", "db_type"])]
+    pub env: Option<String>,
+
+    /// Migrate a protected environment without asking for confirmation
+    #[clap(long)]
+    pub yes: bool,
+
+    /// Run migrations grouped in a single transaction, `=false` overrides a `true` of the config file
+    #[clap(short, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    pub grouped: Option<bool>,
 
     /// Do not actually run migrations, just create and update refinery's schema migration table
     #[clap(short)]
@@ -42,15 +61,99 @@ pub struct MigrateArgs {
     #[clap(short)]
     pub target: Option<SchemaVersion>,
 
-    /// Set migration table name
-    #[clap(long, default_value = "refinery_schema_history")]
-    pub table_name: String,
+    /// Set migration table name [default: the table_name of the config file's [migrations] section, or
+    /// refinery_schema_history]
+    #[clap(long)]
+    pub table_name: Option<String>,
+
+    /// Should abort if divergent migrations are found, `=false` overrides a `true` of the config file
+    #[clap(short, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    pub divergent: Option<bool>,
+
+    /// Should abort if missing migrations are found, `=false` overrides a `true` of the config file
+    #[clap(short, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    pub missing: Option<bool>,
+
+    /// Regular expression migration file names are matched against, with `version` and `name` named captures
+    #[clap(long)]
+    pub naming: Option<String>,
+
+    /// Record a snapshot of the schema after migrating, for `refinery drift` to compare against
+    #[clap(long)]
+    pub snapshot: bool,
+
+    /// Write the schema to the given file after migrating, i.e. schema.sql
+    #[clap(long)]
+    pub schema_file: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct SquashArgs {
+    /// Config file location of an empty scratch database the migrations are run on
+    #[clap(short, default_value = "./refinery.toml")]
+    pub config: PathBuf,
+
+    /// Migrations directory path, where the baseline migration is written [default: the path of the config
+    /// file's [migrations] section, or ./migrations]
+    #[clap(short)]
+    pub path: Option<PathBuf>,
 
-    /// Should abort if divergent migrations are found
+    /// Load the scratch database from the given environment variable
     #[clap(short)]
-    pub divergent: bool,
+    pub env_var: Option<String>,
+
+    /// Connect to a database of this type (postgres or mysql) configured only by the libpq or MySQL
+    /// client environment variables and files, i.e. PGHOST or ~/.my.cnf, instead of a config file
+    #[clap(long)]
+    pub db_type: Option<ConfigDbType>,
+
+    /// Use the given [environments.<name>] section of the config file, overriding its [main] section
+    #[clap(long, conflicts_with_all = ["env_var", "db_type"])]
+    pub env: Option<String>,
+
+    /// Migrate a protected environment without asking for confirmation
+    #[clap(long)]
+    pub yes: bool,
+
+    /// Squash the migrations up to, and including, the specified version
+    #[clap(long)]
+    pub up_to: SchemaVersion,
 
-    /// Should abort if missing migrations are found
+    /// Set migration table name [default: the table_name of the config file's [migrations] section, or
+    /// refinery_schema_history]
+    #[clap(long)]
+    pub table_name: Option<String>,
+
+    /// Regular expression migration file names are matched against, with `version` and `name` named captures
+    #[clap(long)]
+    pub naming: Option<String>,
+}
+
+#[derive(Args)]
+pub struct DriftArgs {
+    /// Config file location
+    #[clap(short, default_value = "./refinery.toml")]
+    pub config: PathBuf,
+
+    /// Load database from the given environment variable
     #[clap(short)]
-    pub missing: bool,
+    pub env_var: Option<String>,
+
+    /// Connect to a database of this type (postgres or mysql) configured only by the libpq or MySQL
+    /// client environment variables and files, i.e. PGHOST or ~/.my.cnf, instead of a config file
+    #[clap(long)]
+    pub db_type: Option<ConfigDbType>,
+
+    /// Use the given [environments.<name>] section of the config file, overriding its [main] section
+    #[clap(long, conflicts_with_all = ["env_var", "db_type"])]
+    pub env: Option<String>,
+
+    /// Set migration table name [default: the table_name of the config file's [migrations] section, or
+    /// refinery_schema_history]
+    #[clap(long)]
+    pub table_name: Option<String>,
+
+    /// Print the changes as TOML instead of one per line
+    #[clap(long)]
+    pub toml: bool,
 }
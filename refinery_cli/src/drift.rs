@@ -0,0 +1,84 @@
+use std::path::Path;
+
+use anyhow::{bail, Context};
+use refinery_core::{config::ConfigDbType, schema::SchemaChange};
+
+use crate::cli::DriftArgs;
+use crate::migrate::{config, runner};
+
+pub fn handle_drift_command(args: DriftArgs) -> anyhow::Result<()> {
+    detect_drift(
+        &args.config,
+        args.env_var.as_deref(),
+        args.db_type,
+        args.env.as_deref(),
+        args.table_name.as_deref(),
+        args.toml,
+    )
+}
+
+fn detect_drift(
+    config_location: &Path,
+    env_var_opt: Option<&str>,
+    db_type_opt: Option<ConfigDbType>,
+    env_opt: Option<&str>,
+    table_name_opt: Option<&str>,
+    toml: bool,
+) -> anyhow::Result<()> {
+    let mut config = config(config_location, env_var_opt, db_type_opt, env_opt)?;
+    // migrations aren't needed to compare the schema with the snapshot
+    let runner = runner(&config, &[], table_name_opt);
+
+    let changes = match config.db_type() {
+        ConfigDbType::Mssql => {
+            cfg_if::cfg_if! {
+                // tiberius is an async driver so we spawn tokio runtime to introspect the schema
+                if #[cfg(feature = "mssql")] {
+                    use tokio::runtime::Builder;
+
+                    let runtime = Builder::new_current_thread()
+                        .enable_all()
+                        .build()
+                        .context("Can't start tokio runtime")?;
+
+                    runtime.block_on(runner.detect_drift_async(&mut config))?
+                } else {
+                    unreachable!("the config was checked to be supported when loaded");
+                }
+            }
+        }
+        ConfigDbType::Mysql | ConfigDbType::Postgres | ConfigDbType::Sqlite => {
+            cfg_if::cfg_if! {
+                if #[cfg(any(feature = "mysql", feature = "postgresql", feature = "sqlite"))] {
+                    runner.detect_drift(&mut config)?
+                } else {
+                    unreachable!("the config was checked to be supported when loaded");
+                }
+            }
+        }
+    };
+
+    print_changes(&changes, toml)?;
+    if !changes.is_empty() {
+        bail!("schema drifted from the snapshot recorded after the last migration");
+    }
+    Ok(())
+}
+
+fn print_changes(changes: &[SchemaChange], toml: bool) -> anyhow::Result<()> {
+    if toml {
+        let mut report = toml::Table::new();
+        report.insert(
+            "changes".to_string(),
+            toml::Value::try_from(changes).context("could not serialize schema changes")?,
+        );
+        print!("{}", report);
+    } else if changes.is_empty() {
+        println!("no drift from the schema snapshot");
+    } else {
+        for change in changes {
+            println!("{}", change);
+        }
+    }
+    Ok(())
+}
@@ -1,8 +1,10 @@
 //! Main entry point for the refinery cli tool
 
 mod cli;
+mod drift;
 mod migrate;
 mod setup;
+mod squash;
 
 use anyhow::Error;
 use clap::Parser;
@@ -27,6 +29,8 @@ fn main() -> Result<(), Error> {
     match cli {
         Cli::Setup => setup::handle_setup()?,
         Cli::Migrate(args) => migrate::handle_migration_command(args)?,
+        Cli::Squash(args) => squash::handle_squash_command(args)?,
+        Cli::Drift(args) => drift::handle_drift_command(args)?,
     }
 
     Ok(())
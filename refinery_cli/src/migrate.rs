@@ -1,9 +1,10 @@
-use std::path::Path;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
 
-use anyhow::Context;
+use anyhow::{bail, Context};
 use refinery_core::{
     config::{Config, ConfigDbType},
-    find_migration_files, Migration, MigrationType, SchemaVersion, Target,
+    load_sql_migrations_with_naming, Migration, NamingStrategy, Runner, SchemaVersion, Target,
 };
 
 use crate::cli::MigrateArgs;
@@ -17,47 +18,55 @@ pub fn handle_migration_command(args: MigrateArgs) -> anyhow::Result<()> {
         args.fake,
         args.target,
         args.env_var.as_deref(),
-        &args.path,
-        &args.table_name,
+        args.db_type,
+        args.env.as_deref(),
+        args.yes,
+        args.path.as_deref(),
+        args.table_name.as_deref(),
+        args.naming.as_deref(),
+        args.snapshot,
+        args.schema_file.as_deref(),
     )
 }
 
 #[allow(clippy::too_many_arguments)]
 fn run_migrations(
     config_location: &Path,
-    grouped: bool,
-    divergent: bool,
-    missing: bool,
+    grouped: Option<bool>,
+    divergent: Option<bool>,
+    missing: Option<bool>,
     fake: bool,
     target: Option<SchemaVersion>,
     env_var_opt: Option<&str>,
-    path: &Path,
-    table_name: &str,
+    db_type_opt: Option<ConfigDbType>,
+    env_opt: Option<&str>,
+    yes: bool,
+    path_opt: Option<&Path>,
+    table_name_opt: Option<&str>,
+    naming_opt: Option<&str>,
+    snapshot: bool,
+    schema_file: Option<&Path>,
 ) -> anyhow::Result<()> {
-    let migration_files_path = find_migration_files(path, MigrationType::Sql)?;
-    let mut migrations = Vec::new();
-    for path in migration_files_path {
-        let sql = std::fs::read_to_string(path.as_path())
-            .with_context(|| format!("could not read migration file name {}", path.display()))?;
+    let mut config = config(config_location, env_var_opt, db_type_opt, env_opt)?;
+    confirm_protected(&config, env_opt, yes)?;
+    let naming = naming_strategy(naming_opt)?;
+    let path = migrations_path(path_opt, &config);
+    let migrations = load_sql_migrations_with_naming(&path, &naming)
+        .with_context(|| format!("could not load migrations from {}", path.display()))?;
 
-        //safe to call unwrap as find_migration_filenames returns canonical paths
-        let filename = path
-            .file_stem()
-            .and_then(|file| file.to_os_string().into_string().ok())
-            .unwrap();
-
-        let migration = Migration::unapplied(&filename, &sql)
-            .with_context(|| format!("could not read migration file name {}", path.display()))?;
-        migrations.push(migration);
-    }
-    let mut config = config(config_location, env_var_opt)?;
-
-    let target = match (fake, target) {
+    let settings = config.migrations();
+    let target = match (fake, target.or(settings.target)) {
         (true, None) => Target::Fake,
         (false, None) => Target::Latest,
         (true, Some(version)) => Target::FakeVersion(version),
         (false, Some(version)) => Target::Version(version),
     };
+    // unlike the Runner, the CLI only aborts on divergent or missing migrations when asked to
+    let runner = runner(&config, &migrations, table_name_opt)
+        .set_grouped(grouped.or(settings.grouped).unwrap_or(false))
+        .set_abort_divergent(divergent.or(settings.abort_divergent).unwrap_or(false))
+        .set_abort_missing(missing.or(settings.abort_missing).unwrap_or(false))
+        .set_target(target);
 
     match config.db_type() {
         ConfigDbType::Mssql => {
@@ -72,32 +81,32 @@ fn run_migrations(
                         .context("Can't start tokio runtime")?;
 
                     runtime.block_on(async {
-                        refinery_core::Runner::new(&migrations)
-                            .set_grouped(grouped)
-                            .set_target(target)
-                            .set_abort_divergent(divergent)
-                            .set_abort_missing(missing)
-                            .set_migration_table_name(table_name)
-                            .run_async(&mut config)
-                            .await
+                        runner.run_async(&mut config).await?;
+                        if snapshot {
+                            runner.record_snapshot_async(&mut config).await?;
+                        }
+                        if let Some(schema_file) = schema_file {
+                            runner.dump_schema_async(&mut config, schema_file).await?;
+                        }
+                        Ok::<_, refinery_core::Error>(())
                     })?;
                 } else {
-                    panic!("tried to migrate async from config for a mssql database, but mssql feature was not enabled!");
+                    unreachable!("the config was checked to be supported when loaded");
                 }
             }
         }
-        _db_type @ (ConfigDbType::Mysql | ConfigDbType::Postgres | ConfigDbType::Sqlite) => {
+        ConfigDbType::Mysql | ConfigDbType::Postgres | ConfigDbType::Sqlite => {
             cfg_if::cfg_if! {
                 if #[cfg(any(feature = "mysql", feature = "postgresql", feature = "sqlite"))] {
-                    refinery_core::Runner::new(&migrations)
-                        .set_grouped(grouped)
-                        .set_abort_divergent(divergent)
-                        .set_abort_missing(missing)
-                        .set_target(target)
-                        .set_migration_table_name(table_name)
-                        .run(&mut config)?;
+                    runner.run(&mut config)?;
+                    if snapshot {
+                        runner.record_snapshot(&mut config)?;
+                    }
+                    if let Some(schema_file) = schema_file {
+                        runner.dump_schema(&mut config, schema_file)?;
+                    }
                 } else {
-                    panic!("tried to migrate async from config for a {:?} database, but it's matching feature was not enabled!", _db_type);
+                    unreachable!("the config was checked to be supported when loaded");
                 }
             }
         }
@@ -105,10 +114,87 @@ fn run_migrations(
     Ok(())
 }
 
-fn config(config_location: &Path, env_var_opt: Option<&str>) -> anyhow::Result<Config> {
-    if let Some(env_var) = env_var_opt {
-        Config::from_env_var(env_var).context("could not environment variable")
+// The migrations directory given on the command line, else the one of the config file
+pub(crate) fn migrations_path(path_opt: Option<&Path>, config: &Config) -> PathBuf {
+    path_opt
+        .or(config.migrations().path.as_deref())
+        .unwrap_or(Path::new("./migrations"))
+        .to_path_buf()
+}
+
+// A Runner with the settings of the config file, and the table name given on the command line
+pub(crate) fn runner(
+    config: &Config,
+    migrations: &[Migration],
+    table_name_opt: Option<&str>,
+) -> Runner {
+    let mut runner = Runner::from_config(config, migrations);
+    if let Some(table_name) = table_name_opt {
+        runner.set_migration_table_name(table_name);
+    }
+    runner
+}
+
+pub(crate) fn naming_strategy(naming_opt: Option<&str>) -> anyhow::Result<NamingStrategy> {
+    match naming_opt {
+        Some(pattern) => {
+            NamingStrategy::new(pattern).context("could not parse the migration naming pattern")
+        }
+        None => Ok(NamingStrategy::default()),
+    }
+}
+
+pub(crate) fn config(
+    config_location: &Path,
+    env_var_opt: Option<&str>,
+    db_type_opt: Option<ConfigDbType>,
+    env_opt: Option<&str>,
+) -> anyhow::Result<Config> {
+    let config = if let Some(env_var) = env_var_opt {
+        Config::from_env_var(env_var).context("could not environment variable")?
+    } else if let Some(db_type) = db_type_opt {
+        cfg_if::cfg_if! {
+            if #[cfg(any(feature = "postgresql", feature = "mysql"))] {
+                Config::from_standard_env(db_type).context("could not read the standard environment")?
+            } else {
+                anyhow::bail!("{db_type:?} can't be configured from the standard environment, enable the postgresql or mysql feature")
+            }
+        }
+    } else if let Some(env) = env_opt {
+        Config::from_file_location_env(config_location, env)
+            .context("could not parse the config file")?
     } else {
-        Config::from_file_location(config_location).context("could not parse the config file")
+        Config::from_file_location(config_location).context("could not parse the config file")?
+    };
+    // fill in what isn't configured from the libpq or MySQL client environment variables and files
+    #[cfg(any(feature = "postgresql", feature = "mysql"))]
+    let config = config
+        .with_standard_env()
+        .context("could not read the standard environment")?;
+    // tiberius is an async driver, the other databases are migrated synchronously
+    config.check_supported(config.db_type() == ConfigDbType::Mssql)?;
+    Ok(config)
+}
+
+// Have the environment name typed before changing the database of a protected environment
+pub(crate) fn confirm_protected(
+    config: &Config,
+    env_opt: Option<&str>,
+    yes: bool,
+) -> anyhow::Result<()> {
+    if !config.is_protected() || yes {
+        return Ok(());
+    }
+    let name = env_opt.unwrap_or("main");
+    if !io::stdin().is_terminal() {
+        bail!("the {name} environment is protected, pass --yes to migrate it without confirmation");
     }
+    print!("The {name} environment is protected, type its name to migrate it: ");
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    if answer.trim() != name {
+        bail!("migrating the {name} environment wasn't confirmed");
+    }
+    Ok(())
 }
@@ -43,6 +43,8 @@ fn get_config_from_input() -> Result<Config> {
         _ => return Err(anyhow!("invalid option")),
     };
     let mut config = Config::new(db_type);
+    // tiberius is an async driver, the other databases are migrated synchronously
+    config.check_supported(db_type == ConfigDbType::Mssql)?;
 
     if config.db_type() == ConfigDbType::Sqlite {
         cfg_if::cfg_if! {
@@ -54,7 +56,7 @@ fn get_config_from_input() -> Result<Config> {
                 config = config.set_db_path(db_path.trim());
                 return Ok(config);
             } else {
-                panic!("tried to migrate async from config for a sqlite database, but sqlite feature was not enabled!");
+                unreachable!("the config was checked to be supported");
             }
         }
     }
@@ -0,0 +1,82 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::Context;
+use refinery_core::{config::ConfigDbType, load_sql_migrations_with_naming, SchemaVersion};
+
+use crate::cli::SquashArgs;
+use crate::migrate::{config, confirm_protected, migrations_path, naming_strategy, runner};
+
+pub fn handle_squash_command(args: SquashArgs) -> anyhow::Result<()> {
+    squash_migrations(
+        &args.config,
+        args.env_var.as_deref(),
+        args.db_type,
+        args.env.as_deref(),
+        args.yes,
+        args.path.as_deref(),
+        args.up_to,
+        args.table_name.as_deref(),
+        args.naming.as_deref(),
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+fn squash_migrations(
+    config_location: &Path,
+    env_var_opt: Option<&str>,
+    db_type_opt: Option<ConfigDbType>,
+    env_opt: Option<&str>,
+    yes: bool,
+    path_opt: Option<&Path>,
+    up_to: SchemaVersion,
+    table_name_opt: Option<&str>,
+    naming_opt: Option<&str>,
+) -> anyhow::Result<()> {
+    let mut config = config(config_location, env_var_opt, db_type_opt, env_opt)?;
+    confirm_protected(&config, env_opt, yes)?;
+    let naming = naming_strategy(naming_opt)?;
+    let path = migrations_path(path_opt, &config);
+    let migrations = load_sql_migrations_with_naming(&path, &naming)
+        .with_context(|| format!("could not load migrations from {}", path.display()))?;
+    let runner = runner(&config, &migrations, table_name_opt);
+
+    let baseline = match config.db_type() {
+        ConfigDbType::Mssql => {
+            cfg_if::cfg_if! {
+                // tiberius is an async driver so we spawn tokio runtime and run the migrations
+                if #[cfg(feature = "mssql")] {
+                    use tokio::runtime::Builder;
+
+                    let runtime = Builder::new_current_thread()
+                        .enable_all()
+                        .build()
+                        .context("Can't start tokio runtime")?;
+
+                    runtime.block_on(async {
+                        runner.squash_async(&mut config, up_to)
+                            .await
+                    })?
+                } else {
+                    unreachable!("the config was checked to be supported when loaded");
+                }
+            }
+        }
+        ConfigDbType::Mysql | ConfigDbType::Postgres | ConfigDbType::Sqlite => {
+            cfg_if::cfg_if! {
+                if #[cfg(any(feature = "mysql", feature = "postgresql", feature = "sqlite"))] {
+                    runner.squash(&mut config, up_to)?
+                } else {
+                    unreachable!("the config was checked to be supported when loaded");
+                }
+            }
+        }
+    };
+
+    let location = path.join(format!("{}.sql", baseline));
+    // safe to call unwrap as the baseline was just created from sql
+    fs::write(&location, baseline.sql().unwrap())
+        .with_context(|| format!("could not write baseline to {}", location.display()))?;
+    println!("squashed migrations into {}", location.display());
+    Ok(())
+}
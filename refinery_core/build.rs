@@ -0,0 +1,17 @@
+// mysql, mysql_async and tiberius don't build with both their native-tls and rustls backends, fail with
+// one error here, as the build script runs before them, instead of their own wall of type errors
+fn main() {
+    println!("cargo::rerun-if-changed=build.rs");
+    let enabled = |feature: &str| std::env::var_os(format!("CARGO_FEATURE_{feature}")).is_some();
+    if !(enabled("NATIVE_TLS") && enabled("RUSTLS")) {
+        return;
+    }
+    for driver in ["MYSQL", "MYSQL_ASYNC", "TIBERIUS"] {
+        if enabled(driver) {
+            let driver = driver.to_lowercase();
+            println!(
+                "cargo::error=features `native-tls` and `rustls` are mutually exclusive with `{driver}`, enable only one TLS backend"
+            );
+        }
+    }
+}
@@ -0,0 +1,223 @@
+use crate::config::ConfigDbType;
+use crate::error::WrapMigrationError;
+use crate::schema::{Schema, SchemaRows};
+use crate::traits::r#async::{AsyncMigrate, AsyncQuery, AsyncTransaction};
+use crate::{Error, Migration, Report, Target};
+use async_trait::async_trait;
+use bb8::{ManageConnection, Pool, PooledConnection};
+
+// a pooled connection migrates as the connection it wraps
+#[async_trait]
+impl<M> AsyncTransaction for PooledConnection<'_, M>
+where
+    M: ManageConnection,
+    M::Connection: AsyncTransaction,
+{
+    type Error = <M::Connection as AsyncTransaction>::Error;
+
+    async fn execute<'a, S: AsRef<str> + Send, T: Iterator<Item = S> + Send>(
+        &mut self,
+        queries: T,
+    ) -> Result<usize, Self::Error> {
+        (**self).execute(queries).await
+    }
+
+    async fn execute_outside_transaction<S: AsRef<str> + Send, T: Iterator<Item = S> + Send>(
+        &mut self,
+        queries: T,
+    ) -> Result<usize, Self::Error> {
+        (**self).execute_outside_transaction(queries).await
+    }
+}
+
+#[async_trait]
+impl<M, T> AsyncQuery<T> for PooledConnection<'_, M>
+where
+    M: ManageConnection,
+    M::Connection: AsyncQuery<T>,
+{
+    async fn query(&mut self, query: &str) -> Result<T, <Self as AsyncTransaction>::Error> {
+        (**self).query(query).await
+    }
+}
+
+impl<M> AsyncMigrate for PooledConnection<'_, M>
+where
+    M: ManageConnection,
+    M::Connection: AsyncMigrate,
+{
+    fn assert_migrations_table_query(migration_table_name: &str) -> String {
+        M::Connection::assert_migrations_table_query(migration_table_name)
+    }
+
+    fn get_last_applied_migration_query(migration_table_name: &str) -> String {
+        M::Connection::get_last_applied_migration_query(migration_table_name)
+    }
+
+    fn get_applied_migrations_query(migration_table_name: &str) -> String {
+        M::Connection::get_applied_migrations_query(migration_table_name)
+    }
+
+    fn dialect(&self) -> Option<ConfigDbType> {
+        (**self).dialect()
+    }
+}
+
+async fn checkout<M>(pool: &Pool<M>) -> Result<PooledConnection<'_, M>, Error>
+where
+    M: ManageConnection,
+    M::Error: std::error::Error + Sync,
+{
+    pool.get().await.migration_err(
+        || "could not get a connection from the pool",
+        || [].into_iter(),
+    )
+}
+
+// the pool checks out a connection for every query, and a single one for every migration cycle.
+// As the pool can't tell its dialect without checking out a connection, which is async,
+// squash and dump the schema through a connection checked out with `Pool::get`.
+#[async_trait]
+impl<M> AsyncTransaction for Pool<M>
+where
+    M: ManageConnection,
+    M::Connection: AsyncTransaction,
+    M::Error: std::error::Error + Sync,
+{
+    type Error = Error;
+
+    async fn execute<'a, S: AsRef<str> + Send, T: Iterator<Item = S> + Send>(
+        &mut self,
+        queries: T,
+    ) -> Result<usize, Self::Error> {
+        checkout(self)
+            .await?
+            .execute(queries)
+            .await
+            .migration_err(|| "error executing queries", || [].into_iter())
+    }
+
+    async fn execute_outside_transaction<S: AsRef<str> + Send, T: Iterator<Item = S> + Send>(
+        &mut self,
+        queries: T,
+    ) -> Result<usize, Self::Error> {
+        checkout(self)
+            .await?
+            .execute_outside_transaction(queries)
+            .await
+            .migration_err(|| "error executing queries", || [].into_iter())
+    }
+}
+
+#[async_trait]
+impl<M, T> AsyncQuery<T> for Pool<M>
+where
+    M: ManageConnection,
+    M::Connection: AsyncQuery<T>,
+    M::Error: std::error::Error + Sync,
+{
+    async fn query(&mut self, query: &str) -> Result<T, <Self as AsyncTransaction>::Error> {
+        checkout(self)
+            .await?
+            .query(query)
+            .await
+            .migration_err(|| "error running query", || [].into_iter())
+    }
+}
+
+#[async_trait]
+impl<M> AsyncMigrate for Pool<M>
+where
+    M: ManageConnection,
+    M::Connection: AsyncMigrate + AsyncQuery<SchemaRows>,
+    M::Error: std::error::Error + Sync,
+{
+    fn assert_migrations_table_query(migration_table_name: &str) -> String {
+        M::Connection::assert_migrations_table_query(migration_table_name)
+    }
+
+    fn get_last_applied_migration_query(migration_table_name: &str) -> String {
+        M::Connection::get_last_applied_migration_query(migration_table_name)
+    }
+
+    fn get_applied_migrations_query(migration_table_name: &str) -> String {
+        M::Connection::get_applied_migrations_query(migration_table_name)
+    }
+
+    async fn introspect_schema(&mut self, migration_table_name: &str) -> Result<Schema, Error>
+    where
+        Self: AsyncQuery<SchemaRows>,
+    {
+        checkout(self)
+            .await?
+            .introspect_schema(migration_table_name)
+            .await
+    }
+
+    #[cfg(feature = "toml")]
+    async fn record_schema_snapshot(&mut self, migration_table_name: &str) -> Result<Schema, Error>
+    where
+        Self: AsyncQuery<SchemaRows>,
+    {
+        checkout(self)
+            .await?
+            .record_schema_snapshot(migration_table_name)
+            .await
+    }
+
+    #[cfg(feature = "toml")]
+    async fn get_schema_snapshot(
+        &mut self,
+        migration_table_name: &str,
+    ) -> Result<Option<(Option<String>, Schema)>, Error>
+    where
+        Self: AsyncQuery<SchemaRows>,
+    {
+        checkout(self)
+            .await?
+            .get_schema_snapshot(migration_table_name)
+            .await
+    }
+
+    async fn get_last_applied_migration(
+        &mut self,
+        migration_table_name: &str,
+    ) -> Result<Option<Migration>, Error> {
+        checkout(self)
+            .await?
+            .get_last_applied_migration(migration_table_name)
+            .await
+    }
+
+    async fn get_applied_migrations(
+        &mut self,
+        migration_table_name: &str,
+    ) -> Result<Vec<Migration>, Error> {
+        checkout(self)
+            .await?
+            .get_applied_migrations(migration_table_name)
+            .await
+    }
+
+    async fn migrate(
+        &mut self,
+        migrations: &[Migration],
+        abort_divergent: bool,
+        abort_missing: bool,
+        grouped: bool,
+        target: Target,
+        migration_table_name: &str,
+    ) -> Result<Report, Error> {
+        checkout(self)
+            .await?
+            .migrate(
+                migrations,
+                abort_divergent,
+                abort_missing,
+                grouped,
+                target,
+                migration_table_name,
+            )
+            .await
+    }
+}
@@ -1,23 +1,122 @@
-use crate::config::Config;
+use crate::config::{Config, ConfigDbType};
+use crate::error::Kind;
+use crate::schema::SchemaRows;
 use crate::traits::r#async::{AsyncQuery, AsyncTransaction};
 use crate::traits::sync::{Query, Transaction};
-use crate::Migration;
 #[cfg(any(
     feature = "mysql",
     feature = "postgres",
     feature = "rusqlite",
     feature = "tokio-postgres",
     feature = "mysql_async",
+    feature = "sqlx",
     feature = "tiberius-config"
 ))]
 use crate::{
-    config::ConfigDbType,
+    config::Retry,
     error::WrapMigrationError,
+    schema::Schema,
     traits::{GET_APPLIED_MIGRATIONS_QUERY, GET_LAST_APPLIED_MIGRATION_QUERY},
-    Error, Report, Target,
+    Report, Target,
 };
+use crate::{Error, Migration};
 use async_trait::async_trait;
 use std::convert::Infallible;
+#[cfg(any(
+    feature = "mysql",
+    feature = "postgres",
+    feature = "rusqlite",
+    feature = "tokio-postgres",
+    feature = "mysql_async",
+    feature = "sqlx",
+    feature = "tiberius-config"
+))]
+use std::time::Instant;
+
+impl Config {
+    /// Check whether this build of refinery can migrate the configured database, synchronously with
+    /// `Runner::run` or asynchronously with `Runner::run_async`, as each database requires its driver feature.
+    /// Useful to validate a config loaded with [`Config::from_file_location`] before migrating.
+    pub fn check_supported(&self, asynchronous: bool) -> Result<(), Error> {
+        let supported = match (self.db_type(), asynchronous) {
+            (ConfigDbType::Mysql, false) => cfg!(feature = "mysql"),
+            (ConfigDbType::Mysql, true) => cfg!(any(feature = "mysql_async", feature = "sqlx")),
+            (ConfigDbType::Sqlite, false) => cfg!(feature = "rusqlite"),
+            (ConfigDbType::Sqlite, true) => cfg!(any(feature = "rusqlite-async", feature = "sqlx")),
+            (ConfigDbType::Postgres, false) => cfg!(feature = "postgres"),
+            (ConfigDbType::Postgres, true) => {
+                cfg!(any(feature = "tokio-postgres", feature = "sqlx"))
+            }
+            (ConfigDbType::Mssql, false) => false,
+            (ConfigDbType::Mssql, true) => cfg!(feature = "tiberius-config"),
+        };
+        if supported {
+            Ok(())
+        } else {
+            Err(unsupported_database(self.db_type(), asynchronous))
+        }
+    }
+}
+
+// An in-memory SQLite database only lives as long as the connection that opened it, and as each
+// operation on a Config opens its own connection, only a single migrate can be run on one
+#[cfg(any(
+    feature = "mysql",
+    feature = "postgres",
+    feature = "rusqlite",
+    feature = "tokio-postgres",
+    feature = "mysql_async",
+    feature = "sqlx",
+    feature = "tiberius-config"
+))]
+fn assert_persistent_database(config: &Config) -> Result<(), Error> {
+    if config.db_type() != ConfigDbType::Sqlite {
+        return Ok(());
+    }
+    cfg_if::cfg_if! {
+        if #[cfg(any(feature = "rusqlite", feature = "sqlx"))] {
+            let in_memory = config.db_path().and_then(|path| path.to_str()).is_some_and(|path| {
+                let (path, params) = path.split_once('?').unwrap_or((path, ""));
+                path == ":memory:"
+                    || path == "file::memory:"
+                    || (path.starts_with("file:") && params.split('&').any(|param| param == "mode=memory"))
+            });
+            if in_memory {
+                return Err(Error::new(
+                    Kind::ConfigError(
+                        "an in-memory SQLite database can only be migrated by a single run, open a connection and pass it to the Runner instead"
+                            .into(),
+                    ),
+                    None,
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+// Get the error for a database whose driver feature isn't enabled
+fn unsupported_database(db_type: ConfigDbType, asynchronous: bool) -> Error {
+    let feature = match (db_type, asynchronous) {
+        (ConfigDbType::Mysql, false) => "mysql",
+        (ConfigDbType::Mysql, true) => "mysql_async or sqlx",
+        (ConfigDbType::Sqlite, false) => "rusqlite",
+        (ConfigDbType::Sqlite, true) => "rusqlite-async or sqlx",
+        (ConfigDbType::Postgres, false) => "postgres",
+        (ConfigDbType::Postgres, true) => "tokio-postgres or sqlx",
+        (ConfigDbType::Mssql, false) => {
+            return Error::new(
+                Kind::ConfigError(
+                    "mssql databases can only be migrated asynchronously, as tiberius is an async driver"
+                        .into(),
+                ),
+                None,
+            )
+        }
+        (ConfigDbType::Mssql, true) => "tiberius-config",
+    };
+    Error::new(Kind::UnsupportedDatabase(db_type, feature), None)
+}
 
 // we impl all the dependent traits as noop's and then override the methods that call them on Migrate and AsyncMigrate
 impl Transaction for Config {
@@ -37,6 +136,12 @@ impl Query<Vec<Migration>> for Config {
     }
 }
 
+impl Query<SchemaRows> for Config {
+    fn query(&mut self, _query: &str) -> Result<SchemaRows, Self::Error> {
+        Ok(Vec::new())
+    }
+}
+
 #[async_trait]
 impl AsyncTransaction for Config {
     type Error = Infallible;
@@ -58,157 +163,389 @@ impl AsyncQuery<Vec<Migration>> for Config {
         Ok(Vec::new())
     }
 }
+
+#[async_trait]
+impl AsyncQuery<SchemaRows> for Config {
+    async fn query(
+        &mut self,
+        _query: &str,
+    ) -> Result<SchemaRows, <Self as AsyncTransaction>::Error> {
+        Ok(Vec::new())
+    }
+}
+
+// Run `op` again while it fails with a transient error, waiting with the backoff of `retry` until its deadline passes
+#[cfg(any(feature = "mysql", feature = "postgres", feature = "rusqlite"))]
+fn with_retry<T>(retry: &Retry, mut op: impl FnMut() -> Result<T, Error>) -> Result<T, Error> {
+    let started = Instant::now();
+    let mut attempt = 0;
+    loop {
+        match op() {
+            Err(err) if err.is_transient() => match retry.backoff(attempt, started.elapsed()) {
+                Some(backoff) => {
+                    log::warn!("{err}, retrying in {}ms", backoff.as_millis());
+                    std::thread::sleep(backoff);
+                    attempt += 1;
+                }
+                None => return Err(err),
+            },
+            result => return result,
+        }
+    }
+}
+
+// Run a query reading the migration history, retrying it on transient errors if `retry` asks to
+#[cfg(any(feature = "mysql", feature = "postgres", feature = "rusqlite"))]
+fn with_query_retry<T>(
+    retry: &Retry,
+    mut op: impl FnMut() -> Result<T, Error>,
+) -> Result<T, Error> {
+    if retry.retry_queries {
+        with_retry(retry, op)
+    } else {
+        op()
+    }
+}
+
+#[cfg(any(
+    feature = "tokio-postgres",
+    feature = "mysql_async",
+    feature = "sqlx",
+    feature = "rusqlite-async",
+    feature = "tiberius-config"
+))]
+async fn with_retry_async<T, F, Fut>(retry: &Retry, mut op: F) -> Result<T, Error>
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = Result<T, Error>>,
+{
+    let started = Instant::now();
+    let mut attempt = 0;
+    loop {
+        match op().await {
+            Err(err) if err.is_transient() => match retry.backoff(attempt, started.elapsed()) {
+                Some(backoff) => {
+                    log::warn!("{err}, retrying in {}ms", backoff.as_millis());
+                    tokio::time::sleep(backoff).await;
+                    attempt += 1;
+                }
+                None => return Err(err),
+            },
+            result => return result,
+        }
+    }
+}
+
+// Give up connecting once the configured connect timeout passed, for the async drivers that don't support one
+#[cfg(any(feature = "mysql_async", feature = "sqlx", feature = "tiberius-config"))]
+async fn with_connect_timeout<T, Fut>(config: &Config, connect: Fut) -> Result<T, Error>
+where
+    Fut: std::future::Future<Output = Result<T, Error>>,
+{
+    match config.connect_timeout() {
+        Some(timeout) => match tokio::time::timeout(timeout, connect).await {
+            Ok(result) => result,
+            Err(_) => Err(std::io::Error::from(std::io::ErrorKind::TimedOut))
+                .migration_err(|| "could not connect to database", || [].into_iter()),
+        },
+        None => connect.await,
+    }
+}
+
+// Connect with tokio-postgres over the given TLS connector, driving the connection on a spawned task
+#[cfg(feature = "tokio-postgres")]
+async fn connect_tokio_postgres<T>(
+    config: &Config,
+    connector: T,
+) -> Result<tokio_postgres::Client, Error>
+where
+    T: tokio_postgres::tls::MakeTlsConnect<tokio_postgres::Socket> + Clone + Send + 'static,
+    T::Stream: Send + 'static,
+    T::TlsConnect: Send,
+    <T::TlsConnect as tokio_postgres::tls::TlsConnect<tokio_postgres::Socket>>::Future: Send,
+{
+    let pg_config = crate::config::tokio_postgres_config(config)?;
+    let (client, connection) = with_retry_async(config.retry(), || {
+        let (pg_config, connector) = (&pg_config, connector.clone());
+        async move {
+            pg_config
+                .connect(connector)
+                .await
+                .migration_err(|| "could not connect to database", || [].into_iter())
+        }
+    })
+    .await?;
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            eprintln!("connection error: {}", e);
+        }
+    });
+    Ok(client)
+}
+
+#[cfg(any(
+    feature = "tokio-postgres",
+    feature = "mysql_async",
+    feature = "sqlx",
+    feature = "rusqlite-async",
+    feature = "tiberius-config"
+))]
+async fn with_query_retry_async<T, F, Fut>(retry: &Retry, mut op: F) -> Result<T, Error>
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = Result<T, Error>>,
+{
+    if retry.retry_queries {
+        with_retry_async(retry, op).await
+    } else {
+        op().await
+    }
+}
+
 // this is written as macro so that we don't have to deal with type signatures
 #[cfg(any(feature = "mysql", feature = "postgres", feature = "rusqlite"))]
 #[allow(clippy::redundant_closure_call)]
 macro_rules! with_connection {
     ($config:ident, $op: expr) => {
+        // drivers may echo the url they connect to in their errors, keep the password out of them
         #[allow(clippy::redundant_closure_call)]
-        match $config.db_type() {
+        (|| match $config.db_type() {
             ConfigDbType::Mysql => {
                 cfg_if::cfg_if! {
                     if #[cfg(feature = "mysql")] {
-                        let url = crate::config::build_db_url("mysql", &$config);
-                        let opts = mysql::Opts::from_url(&url).migration_err(|| "could not parse url", || [].into_iter())?;
-                        let conn = mysql::Conn::new(opts).migration_err(|| "could not connect to database", || [].into_iter())?;
+                        let opts = crate::config::mysql_opts(&$config)?;
+                        let conn = with_retry($config.retry(), || mysql::Conn::new(opts.clone()).migration_err(|| "could not connect to database", || [].into_iter()))?;
                         $op(conn)
                     } else {
-                        panic!("tried to migrate from config for a mysql database, but feature mysql not enabled!");
+                        Err(unsupported_database(ConfigDbType::Mysql, false))
                     }
                 }
             }
             ConfigDbType::Sqlite => {
                 cfg_if::cfg_if! {
                     if #[cfg(feature = "rusqlite")] {
-                        //may have been checked earlier on config parsing, even if not let it fail with a Rusqlite db file not found error
-                        let path = $config.db_path().map(|p| p.to_path_buf()).unwrap_or_default();
-                        let conn = rusqlite::Connection::open_with_flags(path, rusqlite::OpenFlags::SQLITE_OPEN_READ_WRITE).migration_err(|| "could not open database", || [].into_iter())?;
+                        let conn = with_retry($config.retry(), || crate::config::open_rusqlite(&$config))?;
                         $op(conn)
                     } else {
-                        panic!("tried to migrate from config for a sqlite database, but feature rusqlite not enabled!");
+                        Err(unsupported_database(ConfigDbType::Sqlite, false))
                     }
                 }
             }
             ConfigDbType::Postgres => {
                 cfg_if::cfg_if! {
                     if #[cfg(feature = "postgres")] {
-                        let path = crate::config::build_db_url("postgresql", &$config);
+                        let pg_config = crate::config::postgres_config(&$config)?;
 
-                        let conn;
-                        if $config.use_tls() {
-                            let connector = native_tls::TlsConnector::new().unwrap();
-                            let connector = postgres_native_tls::MakeTlsConnector::new(connector);
-                            conn = postgres::Client::connect(path.as_str(), connector).migration_err(|| "could not connect to database", || [].into_iter())?;
-                        } else {
-                            conn = postgres::Client::connect(path.as_str(), postgres::NoTls).migration_err(|| "could not connect to database", || [].into_iter())?;
-                        }
+                        let conn = with_retry($config.retry(), || {
+                            if $config.use_tls() {
+                                cfg_if::cfg_if! {
+                                    if #[cfg(feature = "postgres-native-tls")] {
+                                        let connector = crate::config::native_tls_connector(&$config)?;
+                                        let connector = postgres_native_tls::MakeTlsConnector::new(connector);
+                                        pg_config.connect(connector).migration_err(|| "could not connect to database", || [].into_iter())
+                                    } else if #[cfg(feature = "postgres-rustls")] {
+                                        let connector = crate::config::rustls_connector(&$config)?;
+                                        pg_config.connect(connector).migration_err(|| "could not connect to database", || [].into_iter())
+                                    } else {
+                                        Err(Error::new(Kind::ConfigError("TLS isn't supported by this build, enable the postgres-native-tls or postgres-rustls feature".into()), None))
+                                    }
+                                }
+                            } else {
+                                pg_config.connect(postgres::NoTls).migration_err(|| "could not connect to database", || [].into_iter())
+                            }
+                        })?;
 
                         $op(conn)
                     } else {
-                        panic!("tried to migrate from config for a postgresql database, but feature postgres not enabled!");
+                        Err(unsupported_database(ConfigDbType::Postgres, false))
                     }
                 }
             }
             ConfigDbType::Mssql => {
-                panic!("tried to synchronously migrate from config for a mssql database, but tiberius is an async driver");
+                Err(unsupported_database(ConfigDbType::Mssql, false))
             }
-        }
+        })()
+        .map_err(|err| $config.redact_error(err))
     }
 }
 
 #[cfg(any(
     feature = "tokio-postgres",
     feature = "mysql_async",
+    feature = "sqlx",
+    feature = "rusqlite-async",
     feature = "tiberius-config"
 ))]
 macro_rules! with_connection_async {
     ($config: ident, $op: expr) => {
+        // drivers may echo the url they connect to in their errors, keep the password out of them
         #[allow(clippy::redundant_closure_call)]
-        match $config.db_type() {
+        async { match $config.db_type() {
             ConfigDbType::Mysql => {
                 cfg_if::cfg_if! {
                     if #[cfg(feature = "mysql_async")] {
-                        let url = crate::config::build_db_url("mysql", $config);
-                        let pool = mysql_async::Pool::from_url(&url).migration_err(|| "could not connect to the database", || [].into_iter())?;
+                        let pool = mysql_async::Pool::new(crate::config::mysql_async_opts($config)?);
+                        // the pool connects lazily, wait for the database to accept a connection before handing it over
+                        with_retry_async($config.retry(), || with_connect_timeout($config, async {
+                            pool.get_conn().await.migration_err(|| "could not connect to the database", || [].into_iter())
+                        })).await?;
                         $op(pool).await
+                    } else if #[cfg(feature = "sqlx")] {
+                        use sqlx::ConnectOptions;
+
+                        let options = crate::config::sqlx_mysql_options($config)?;
+                        let conn = with_retry_async($config.retry(), || with_connect_timeout($config, async {
+                            options.connect().await.migration_err(|| "could not connect to database", || [].into_iter())
+                        })).await?;
+                        $op(conn).await
                     } else {
-                        panic!("tried to migrate async from config for a mysql database, but feature mysql_async not enabled!");
+                        Err(unsupported_database(ConfigDbType::Mysql, true))
                     }
                 }
             }
             ConfigDbType::Sqlite => {
-                panic!("tried to migrate async from config for a sqlite database, but this feature is not implemented yet");
+                cfg_if::cfg_if! {
+                    if #[cfg(feature = "sqlx")] {
+                        use sqlx::ConnectOptions;
+
+                        let options = crate::config::sqlx_sqlite_options($config)?;
+                        let conn = with_retry_async($config.retry(), || async {
+                            options.connect().await.migration_err(|| "could not open database", || [].into_iter())
+                        }).await?;
+                        $op(conn).await
+                    } else if #[cfg(feature = "rusqlite-async")] {
+                        let conn = with_retry_async($config.retry(), || async {
+                            crate::config::open_rusqlite($config)
+                        }).await?;
+                        $op(crate::drivers::rusqlite_async::AsyncRusqliteConnection::new(conn)).await
+                    } else {
+                        Err(unsupported_database(ConfigDbType::Sqlite, true))
+                    }
+                }
             }
             ConfigDbType::Postgres => {
                 cfg_if::cfg_if! {
                     if #[cfg(feature = "tokio-postgres")] {
-                        let path = crate::config::build_db_url("postgresql", $config);
-                        if $config.use_tls() {
-                            let connector = native_tls::TlsConnector::new().unwrap();
-                            let connector = postgres_native_tls::MakeTlsConnector::new(connector);
-                            let (client, connection) = tokio_postgres::connect(path.as_str(), connector).await.migration_err(|| "could not connect to database", || [].into_iter())?;
-                            tokio::spawn(async move {
-                                if let Err(e) = connection.await {
-                                    eprintln!("connection error: {}", e);
+                        let client = if $config.use_tls() {
+                            cfg_if::cfg_if! {
+                                if #[cfg(feature = "postgres-native-tls")] {
+                                    let connector = crate::config::native_tls_connector($config)?;
+                                    connect_tokio_postgres($config, postgres_native_tls::MakeTlsConnector::new(connector)).await?
+                                } else if #[cfg(feature = "postgres-rustls")] {
+                                    connect_tokio_postgres($config, crate::config::rustls_connector($config)?).await?
+                                } else {
+                                    return Err(Error::new(Kind::ConfigError("TLS isn't supported by this build, enable the postgres-native-tls or postgres-rustls feature".into()), None));
                                 }
-                            });
-                            $op(client).await
+                            }
                         } else {
-                            let (client, connection) = tokio_postgres::connect(path.as_str(), tokio_postgres::NoTls).await.migration_err(|| "could not connect to database", || [].into_iter())?;
-                            tokio::spawn(async move {
-                                if let Err(e) = connection.await {
-                                    eprintln!("connection error: {}", e);
-                                }
-                            });
-                            $op(client).await
-                        }
+                            connect_tokio_postgres($config, tokio_postgres::NoTls).await?
+                        };
+                        $op(client).await
+                    } else if #[cfg(feature = "sqlx")] {
+                        use sqlx::ConnectOptions;
+
+                        let options = crate::config::sqlx_postgres_options($config)?;
+                        let conn = with_retry_async($config.retry(), || with_connect_timeout($config, async {
+                            options.connect().await.migration_err(|| "could not connect to database", || [].into_iter())
+                        })).await?;
+                        $op(conn).await
                     } else {
-                        panic!("tried to migrate async from config for a postgresql database, but tokio-postgres was not enabled!");
+                        Err(unsupported_database(ConfigDbType::Postgres, true))
                     }
                 }
             }
             ConfigDbType::Mssql => {
                 cfg_if::cfg_if! {
                     if #[cfg(feature = "tiberius-config")] {
-                        use tiberius::{Client, Config};
+                        use tiberius::{Client, Config, SqlBrowser};
                         use tokio::net::TcpStream;
                         use tokio_util::compat::TokioAsyncWriteCompatExt;
                         use std::convert::TryInto;
 
                         let config: Config = (&*$config).try_into()?;
-                        let tcp = TcpStream::connect(config.get_addr())
-                            .await
-                            .migration_err(|| "could not connect to database", || [].into_iter())?;
-                        let client = Client::connect(config, tcp.compat_write())
-                            .await
-                            .migration_err(|| "could not connect to database", || [].into_iter())?;
+                        let client = with_retry_async($config.retry(), || {
+                            let tconfig = config.clone();
+                            with_connect_timeout($config, async move {
+                                // resolves the port of a named instance through the SQL Server Browser
+                                let tcp = TcpStream::connect_named(&tconfig)
+                                    .await
+                                    .migration_err(|| "could not connect to database", || [].into_iter())?;
+                                Client::connect(tconfig, tcp.compat_write())
+                                    .await
+                                    .migration_err(|| "could not connect to database", || [].into_iter())
+                            })
+                        }).await?;
 
                         $op(client).await
                     } else {
-                        panic!("tried to migrate async from config for a mssql database, but tiberius-config feature was not enabled!");
+                        Err(unsupported_database(ConfigDbType::Mssql, true))
                     }
                 }
             }
-        }
+        }}
+        .await
+        .map_err(|err| $config.redact_error(err))
     }
 }
 
 // rewrite all the default methods as we overrode Transaction and Query
 #[cfg(any(feature = "mysql", feature = "postgres", feature = "rusqlite"))]
 impl crate::Migrate for Config {
+    fn dialect(&self) -> Option<ConfigDbType> {
+        Some(self.db_type())
+    }
+
+    fn introspect_schema(&mut self, migration_table_name: &str) -> Result<Schema, Error>
+    where
+        Self: Query<SchemaRows>,
+    {
+        assert_persistent_database(self)?;
+        with_connection!(self, |mut conn| {
+            crate::Migrate::introspect_schema(&mut conn, migration_table_name)
+        })
+    }
+
+    #[cfg(feature = "toml")]
+    fn record_schema_snapshot(&mut self, migration_table_name: &str) -> Result<Schema, Error>
+    where
+        Self: Query<SchemaRows>,
+    {
+        assert_persistent_database(self)?;
+        with_connection!(self, |mut conn| {
+            crate::Migrate::record_schema_snapshot(&mut conn, migration_table_name)
+        })
+    }
+
+    #[cfg(feature = "toml")]
+    fn get_schema_snapshot(
+        &mut self,
+        migration_table_name: &str,
+    ) -> Result<Option<(Option<String>, Schema)>, Error>
+    where
+        Self: Query<SchemaRows>,
+    {
+        assert_persistent_database(self)?;
+        with_connection!(self, |mut conn| {
+            crate::Migrate::get_schema_snapshot(&mut conn, migration_table_name)
+        })
+    }
+
     fn get_last_applied_migration(
         &mut self,
         migration_table_name: &str,
     ) -> Result<Option<Migration>, Error> {
-        with_connection!(self, |mut conn| {
-            let mut migrations: Vec<Migration> = Query::query(
-                &mut conn,
-                &GET_LAST_APPLIED_MIGRATION_QUERY
-                    .replace("%MIGRATION_TABLE_NAME%", migration_table_name),
-            )
-            .migration_err(|| "error getting last applied migration", || [].into_iter())?;
+        assert_persistent_database(self)?;
+        with_query_retry(self.retry(), || {
+            with_connection!(self, |mut conn| {
+                let migrations: Vec<Migration> = Query::query(
+                    &mut conn,
+                    &GET_LAST_APPLIED_MIGRATION_QUERY
+                        .replace("%MIGRATION_TABLE_NAME%", migration_table_name),
+                )
+                .migration_err(|| "error getting last applied migration", || [].into_iter())?;
 
-            Ok(migrations.pop())
+                Ok(migrations.into_iter().max())
+            })
         })
     }
 
@@ -216,15 +553,18 @@ impl crate::Migrate for Config {
         &mut self,
         migration_table_name: &str,
     ) -> Result<Vec<Migration>, Error> {
-        with_connection!(self, |mut conn| {
-            let migrations: Vec<Migration> = Query::query(
-                &mut conn,
-                &GET_APPLIED_MIGRATIONS_QUERY
-                    .replace("%MIGRATION_TABLE_NAME%", migration_table_name),
-            )
-            .migration_err(|| "error getting applied migrations", || [].into_iter())?;
+        assert_persistent_database(self)?;
+        with_query_retry(self.retry(), || {
+            with_connection!(self, |mut conn| {
+                let migrations: Vec<Migration> = Query::query(
+                    &mut conn,
+                    &GET_APPLIED_MIGRATIONS_QUERY
+                        .replace("%MIGRATION_TABLE_NAME%", migration_table_name),
+                )
+                .migration_err(|| "error getting applied migrations", || [].into_iter())?;
 
-            Ok(migrations)
+                Ok(migrations)
+            })
         })
     }
 
@@ -254,41 +594,92 @@ impl crate::Migrate for Config {
 #[cfg(any(
     feature = "mysql_async",
     feature = "tokio-postgres",
+    feature = "sqlx",
+    feature = "rusqlite-async",
     feature = "tiberius-config"
 ))]
 #[async_trait]
 impl crate::AsyncMigrate for Config {
+    fn dialect(&self) -> Option<ConfigDbType> {
+        Some(self.db_type())
+    }
+
+    async fn introspect_schema(&mut self, migration_table_name: &str) -> Result<Schema, Error>
+    where
+        Self: AsyncQuery<SchemaRows>,
+    {
+        assert_persistent_database(self)?;
+        with_connection_async!(self, move |mut conn| async move {
+            crate::AsyncMigrate::introspect_schema(&mut conn, migration_table_name).await
+        })
+    }
+
+    #[cfg(feature = "toml")]
+    async fn record_schema_snapshot(&mut self, migration_table_name: &str) -> Result<Schema, Error>
+    where
+        Self: AsyncQuery<SchemaRows>,
+    {
+        assert_persistent_database(self)?;
+        with_connection_async!(self, move |mut conn| async move {
+            crate::AsyncMigrate::record_schema_snapshot(&mut conn, migration_table_name).await
+        })
+    }
+
+    #[cfg(feature = "toml")]
+    async fn get_schema_snapshot(
+        &mut self,
+        migration_table_name: &str,
+    ) -> Result<Option<(Option<String>, Schema)>, Error>
+    where
+        Self: AsyncQuery<SchemaRows>,
+    {
+        assert_persistent_database(self)?;
+        with_connection_async!(self, move |mut conn| async move {
+            crate::AsyncMigrate::get_schema_snapshot(&mut conn, migration_table_name).await
+        })
+    }
+
     async fn get_last_applied_migration(
         &mut self,
         migration_table_name: &str,
     ) -> Result<Option<Migration>, Error> {
-        with_connection_async!(self, move |mut conn| async move {
-            let mut migrations: Vec<Migration> = AsyncQuery::query(
-                &mut conn,
-                &GET_LAST_APPLIED_MIGRATION_QUERY
-                    .replace("%MIGRATION_TABLE_NAME%", migration_table_name),
-            )
-            .await
-            .migration_err(|| "error getting last applied migration", || [].into_iter())?;
+        assert_persistent_database(self)?;
+        let config: &Config = self;
+        with_query_retry_async(config.retry(), || async move {
+            with_connection_async!(config, move |mut conn| async move {
+                let migrations: Vec<Migration> = AsyncQuery::query(
+                    &mut conn,
+                    &GET_LAST_APPLIED_MIGRATION_QUERY
+                        .replace("%MIGRATION_TABLE_NAME%", migration_table_name),
+                )
+                .await
+                .migration_err(|| "error getting last applied migration", || [].into_iter())?;
 
-            Ok(migrations.pop())
+                Ok(migrations.into_iter().max())
+            })
         })
+        .await
     }
 
     async fn get_applied_migrations(
         &mut self,
         migration_table_name: &str,
     ) -> Result<Vec<Migration>, Error> {
-        with_connection_async!(self, move |mut conn| async move {
-            let migrations: Vec<Migration> = AsyncQuery::query(
-                &mut conn,
-                &GET_APPLIED_MIGRATIONS_QUERY
-                    .replace("%MIGRATION_TABLE_NAME%", migration_table_name),
-            )
-            .await
-            .migration_err(|| "error getting last applied migration", || [].into_iter())?;
-            Ok(migrations)
+        assert_persistent_database(self)?;
+        let config: &Config = self;
+        with_query_retry_async(config.retry(), || async move {
+            with_connection_async!(config, move |mut conn| async move {
+                let migrations: Vec<Migration> = AsyncQuery::query(
+                    &mut conn,
+                    &GET_APPLIED_MIGRATIONS_QUERY
+                        .replace("%MIGRATION_TABLE_NAME%", migration_table_name),
+                )
+                .await
+                .migration_err(|| "error getting last applied migration", || [].into_iter())?;
+                Ok(migrations)
+            })
         })
+        .await
     }
 
     async fn migrate(
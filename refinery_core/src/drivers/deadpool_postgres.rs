@@ -0,0 +1,179 @@
+use crate::config::ConfigDbType;
+use crate::error::WrapMigrationError;
+use crate::schema::{Schema, SchemaRows};
+use crate::traits::r#async::{AsyncMigrate, AsyncQuery, AsyncTransaction};
+use crate::{Error, Migration, Report, Target};
+use async_trait::async_trait;
+use deadpool_postgres::{Object, Pool};
+use tokio_postgres::Client;
+
+// a pooled connection migrates as the tokio-postgres client it wraps
+fn client(object: &mut Object) -> &mut Client {
+    object
+}
+
+#[async_trait]
+impl AsyncTransaction for Object {
+    type Error = <Client as AsyncTransaction>::Error;
+
+    async fn execute<'a, S: AsRef<str> + Send, T: Iterator<Item = S> + Send>(
+        &mut self,
+        queries: T,
+    ) -> Result<usize, Self::Error> {
+        client(self).execute(queries).await
+    }
+
+    async fn execute_outside_transaction<S: AsRef<str> + Send, T: Iterator<Item = S> + Send>(
+        &mut self,
+        queries: T,
+    ) -> Result<usize, Self::Error> {
+        client(self).execute_outside_transaction(queries).await
+    }
+}
+
+#[async_trait]
+impl<T> AsyncQuery<T> for Object
+where
+    Client: AsyncQuery<T>,
+{
+    async fn query(&mut self, query: &str) -> Result<T, <Self as AsyncTransaction>::Error> {
+        AsyncQuery::query(client(self), query).await
+    }
+}
+
+impl AsyncMigrate for Object {
+    fn dialect(&self) -> Option<ConfigDbType> {
+        Some(ConfigDbType::Postgres)
+    }
+}
+
+async fn checkout(pool: &Pool) -> Result<Object, Error> {
+    pool.get().await.migration_err(
+        || "could not get a connection from the pool",
+        || [].into_iter(),
+    )
+}
+
+// the pool checks out a connection for every query, and a single one for every migration cycle
+#[async_trait]
+impl AsyncTransaction for Pool {
+    type Error = Error;
+
+    async fn execute<'a, S: AsRef<str> + Send, T: Iterator<Item = S> + Send>(
+        &mut self,
+        queries: T,
+    ) -> Result<usize, Self::Error> {
+        checkout(self)
+            .await?
+            .execute(queries)
+            .await
+            .migration_err(|| "error executing queries", || [].into_iter())
+    }
+
+    async fn execute_outside_transaction<S: AsRef<str> + Send, T: Iterator<Item = S> + Send>(
+        &mut self,
+        queries: T,
+    ) -> Result<usize, Self::Error> {
+        checkout(self)
+            .await?
+            .execute_outside_transaction(queries)
+            .await
+            .migration_err(|| "error executing queries", || [].into_iter())
+    }
+}
+
+#[async_trait]
+impl<T> AsyncQuery<T> for Pool
+where
+    Client: AsyncQuery<T>,
+{
+    async fn query(&mut self, query: &str) -> Result<T, <Self as AsyncTransaction>::Error> {
+        AsyncQuery::query(&mut checkout(self).await?, query)
+            .await
+            .migration_err(|| "error running query", || [].into_iter())
+    }
+}
+
+#[async_trait]
+impl AsyncMigrate for Pool {
+    fn dialect(&self) -> Option<ConfigDbType> {
+        Some(ConfigDbType::Postgres)
+    }
+
+    async fn introspect_schema(&mut self, migration_table_name: &str) -> Result<Schema, Error>
+    where
+        Self: AsyncQuery<SchemaRows>,
+    {
+        checkout(self)
+            .await?
+            .introspect_schema(migration_table_name)
+            .await
+    }
+
+    #[cfg(feature = "toml")]
+    async fn record_schema_snapshot(&mut self, migration_table_name: &str) -> Result<Schema, Error>
+    where
+        Self: AsyncQuery<SchemaRows>,
+    {
+        checkout(self)
+            .await?
+            .record_schema_snapshot(migration_table_name)
+            .await
+    }
+
+    #[cfg(feature = "toml")]
+    async fn get_schema_snapshot(
+        &mut self,
+        migration_table_name: &str,
+    ) -> Result<Option<(Option<String>, Schema)>, Error>
+    where
+        Self: AsyncQuery<SchemaRows>,
+    {
+        checkout(self)
+            .await?
+            .get_schema_snapshot(migration_table_name)
+            .await
+    }
+
+    async fn get_last_applied_migration(
+        &mut self,
+        migration_table_name: &str,
+    ) -> Result<Option<Migration>, Error> {
+        checkout(self)
+            .await?
+            .get_last_applied_migration(migration_table_name)
+            .await
+    }
+
+    async fn get_applied_migrations(
+        &mut self,
+        migration_table_name: &str,
+    ) -> Result<Vec<Migration>, Error> {
+        checkout(self)
+            .await?
+            .get_applied_migrations(migration_table_name)
+            .await
+    }
+
+    async fn migrate(
+        &mut self,
+        migrations: &[Migration],
+        abort_divergent: bool,
+        abort_missing: bool,
+        grouped: bool,
+        target: Target,
+        migration_table_name: &str,
+    ) -> Result<Report, Error> {
+        checkout(self)
+            .await?
+            .migrate(
+                migrations,
+                abort_divergent,
+                abort_missing,
+                grouped,
+                target,
+                migration_table_name,
+            )
+            .await
+    }
+}
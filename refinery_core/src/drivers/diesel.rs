@@ -0,0 +1,120 @@
+use crate::config::ConfigDbType;
+use crate::traits::sync::{Migrate, Query, Transaction};
+use crate::util::StoredVersion;
+use crate::Migration;
+use diesel::connection::SimpleConnection;
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
+use diesel::sql_types::Text;
+#[cfg(feature = "diesel-mysql")]
+use diesel::MysqlConnection;
+#[cfg(feature = "diesel-postgres")]
+use diesel::PgConnection;
+#[cfg(feature = "diesel-sqlite")]
+use diesel::SqliteConnection;
+use diesel::{sql_query, Connection, QueryableByName, RunQueryDsl};
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+// sql type of the version column on the migrations table
+#[cfg(all(not(feature = "dotted-versions"), not(feature = "int8-versions")))]
+type VersionType = diesel::sql_types::Integer;
+#[cfg(all(not(feature = "dotted-versions"), feature = "int8-versions"))]
+type VersionType = diesel::sql_types::BigInt;
+#[cfg(feature = "dotted-versions")]
+type VersionType = Text;
+
+#[derive(QueryableByName)]
+struct AppliedMigration {
+    #[diesel(sql_type = VersionType)]
+    version: StoredVersion,
+    #[diesel(sql_type = Text)]
+    name: String,
+    #[diesel(sql_type = Text)]
+    applied_on: String,
+    #[diesel(sql_type = Text)]
+    checksum: String,
+}
+
+impl From<AppliedMigration> for Migration {
+    fn from(applied: AppliedMigration) -> Migration {
+        // Safe to call unwrap, as we stored it in RFC3339 format on the database
+        let applied_on = OffsetDateTime::parse(&applied.applied_on, &Rfc3339).unwrap();
+        Migration::from_history(
+            applied.version,
+            applied.name,
+            applied_on,
+            applied
+                .checksum
+                .parse::<u64>()
+                .expect("checksum must be a valid u64"),
+        )
+    }
+}
+
+// diesel doesn't expose the native error code, only its own classification of it
+pub(crate) fn is_transient(err: &DieselError) -> bool {
+    matches!(
+        err,
+        DieselError::DatabaseError(
+            DatabaseErrorKind::SerializationFailure | DatabaseErrorKind::ClosedConnection,
+            _
+        )
+    )
+}
+
+// diesel connections share their api, written as macro so that we don't have to deal with its type signatures
+macro_rules! impl_diesel {
+    ($type: ty, $dialect: expr) => {
+        impl Transaction for $type {
+            type Error = DieselError;
+
+            fn execute<'a, S: AsRef<str>, T: Iterator<Item = S>>(
+                &mut self,
+                queries: T,
+            ) -> Result<usize, Self::Error> {
+                self.transaction(|transaction| {
+                    let mut count = 0;
+                    for query in queries {
+                        transaction.batch_execute(query.as_ref())?;
+                        count += 1;
+                    }
+                    Ok(count)
+                })
+            }
+
+            fn execute_outside_transaction<S: AsRef<str>, T: Iterator<Item = S>>(
+                &mut self,
+                queries: T,
+            ) -> Result<usize, Self::Error> {
+                let mut count = 0;
+                for query in queries {
+                    self.batch_execute(query.as_ref())?;
+                    count += 1;
+                }
+                Ok(count)
+            }
+        }
+
+        impl Query<Vec<Migration>> for $type {
+            fn query(&mut self, query: &str) -> Result<Vec<Migration>, Self::Error> {
+                let applied = self.transaction(|transaction| {
+                    sql_query(query).load::<AppliedMigration>(transaction)
+                })?;
+                Ok(applied.into_iter().map(Migration::from).collect())
+            }
+        }
+
+        impl Migrate for $type {
+            fn dialect(&self) -> Option<ConfigDbType> {
+                Some($dialect)
+            }
+        }
+    };
+}
+
+#[cfg(feature = "diesel-postgres")]
+impl_diesel!(PgConnection, ConfigDbType::Postgres);
+#[cfg(feature = "diesel-mysql")]
+impl_diesel!(MysqlConnection, ConfigDbType::Mysql);
+#[cfg(feature = "diesel-sqlite")]
+impl_diesel!(SqliteConnection, ConfigDbType::Sqlite);
@@ -1,6 +1,9 @@
 #[cfg(feature = "rusqlite")]
 pub mod rusqlite;
 
+#[cfg(feature = "rusqlite-async")]
+pub mod rusqlite_async;
+
 #[cfg(feature = "tokio-postgres")]
 pub mod tokio_postgres;
 
@@ -16,4 +19,152 @@ pub mod mysql;
 #[cfg(feature = "tiberius")]
 pub mod tiberius;
 
+#[cfg(feature = "r2d2")]
+pub mod r2d2;
+
+#[cfg(feature = "bb8")]
+pub mod bb8;
+
+#[cfg(feature = "deadpool-postgres")]
+pub mod deadpool_postgres;
+
+#[cfg(feature = "sqlx")]
+pub mod sqlx;
+
+#[cfg(any(
+    feature = "diesel-postgres",
+    feature = "diesel-mysql",
+    feature = "diesel-sqlite"
+))]
+pub mod diesel;
+
 mod config;
+
+// Get the error code, and the position if any, a database reported for a driver error
+pub(crate) fn native_error(
+    _err: &(dyn std::error::Error + 'static),
+) -> Option<crate::error::NativeError> {
+    #[cfg(feature = "rusqlite")]
+    if let Some(err) = _err.downcast_ref() {
+        return self::rusqlite::native_error(err);
+    }
+    #[cfg(feature = "postgres")]
+    if let Some(err) = _err.downcast_ref() {
+        return self::postgres::native_error(err);
+    }
+    #[cfg(feature = "tokio-postgres")]
+    if let Some(err) = _err.downcast_ref() {
+        return self::tokio_postgres::native_error(err);
+    }
+    #[cfg(feature = "mysql")]
+    if let Some(err) = _err.downcast_ref() {
+        return self::mysql::native_error(err);
+    }
+    #[cfg(feature = "mysql_async")]
+    if let Some(err) = _err.downcast_ref() {
+        return self::mysql_async::native_error(err);
+    }
+    #[cfg(feature = "tiberius")]
+    if let Some(err) = _err.downcast_ref() {
+        return self::tiberius::native_error(err);
+    }
+    #[cfg(feature = "sqlx")]
+    if let Some(err) = _err.downcast_ref() {
+        return self::sqlx::native_error(err);
+    }
+    None
+}
+
+// Whether a driver error is likely to go away when retrying, i.e. the database isn't reachable or doesn't accept
+// connections yet, the connection was lost or the statement lost a lock conflict
+pub(crate) fn is_transient(err: &(dyn std::error::Error + 'static)) -> bool {
+    if let Some(err) = err.downcast_ref() {
+        return is_transient_io(err);
+    }
+    #[cfg(feature = "rusqlite")]
+    if let Some(err) = err.downcast_ref() {
+        return self::rusqlite::is_transient(err);
+    }
+    #[cfg(feature = "postgres")]
+    if let Some(err) = err.downcast_ref() {
+        return self::postgres::is_transient(err);
+    }
+    #[cfg(feature = "tokio-postgres")]
+    if let Some(err) = err.downcast_ref() {
+        return self::tokio_postgres::is_transient(err);
+    }
+    #[cfg(feature = "mysql")]
+    if let Some(err) = err.downcast_ref() {
+        return self::mysql::is_transient(err);
+    }
+    #[cfg(feature = "mysql_async")]
+    if let Some(err) = err.downcast_ref() {
+        return self::mysql_async::is_transient(err);
+    }
+    #[cfg(feature = "tiberius")]
+    if let Some(err) = err.downcast_ref() {
+        return self::tiberius::is_transient(err);
+    }
+    #[cfg(feature = "sqlx")]
+    if let Some(err) = err.downcast_ref() {
+        return self::sqlx::is_transient(err);
+    }
+    #[cfg(any(
+        feature = "diesel-postgres",
+        feature = "diesel-mysql",
+        feature = "diesel-sqlite"
+    ))]
+    if let Some(err) = err.downcast_ref() {
+        return self::diesel::is_transient(err);
+    }
+    false
+}
+
+// Postgres reports connection failures with the SQLSTATE class 08, a starting up or shutting down server with 57P0x,
+// too many connections with 53300 and lost lock conflicts with 40001 and 40P01
+#[cfg(any(feature = "postgres", feature = "tokio-postgres", feature = "sqlx"))]
+fn is_transient_sqlstate(code: &str) -> bool {
+    code.starts_with("08")
+        || code.starts_with("57P0")
+        || matches!(code, "53300" | "40001" | "40P01")
+}
+
+// Mysql reports too many connections with 1040, a shutting down server with 1053
+// and lost lock conflicts with 1205 and 1213
+#[cfg(any(feature = "mysql", feature = "mysql_async", feature = "sqlx"))]
+fn is_transient_mysql_error(code: u16) -> bool {
+    matches!(code, 1040 | 1053 | 1205 | 1213)
+}
+
+// Get where Mysql reported a syntax error (ER_PARSE_ERROR), its message ends with the sql following the error,
+// cut off after 80 characters, and the line within the failing statement: `... near 'sql' at line 2`
+#[cfg(any(feature = "mysql", feature = "mysql_async", feature = "sqlx"))]
+fn mysql_reported_position(code: u16, message: &str) -> Option<crate::error::ReportedPosition> {
+    if code != 1064 {
+        return None;
+    }
+    let (rest, line) = message.rsplit_once("' at line ")?;
+    let line = line.trim().parse().ok()?;
+    let near = rest.split_once(" near '").map_or("", |(_, near)| near);
+    Some(crate::error::ReportedPosition::Near(near.to_string(), line))
+}
+
+fn is_transient_io(err: &std::io::Error) -> bool {
+    is_transient_io_kind(err.kind())
+}
+
+fn is_transient_io_kind(kind: std::io::ErrorKind) -> bool {
+    use std::io::ErrorKind;
+    matches!(
+        kind,
+        ErrorKind::ConnectionRefused
+            | ErrorKind::ConnectionReset
+            | ErrorKind::ConnectionAborted
+            | ErrorKind::NotConnected
+            | ErrorKind::AddrNotAvailable
+            | ErrorKind::BrokenPipe
+            | ErrorKind::TimedOut
+            | ErrorKind::UnexpectedEof
+            | ErrorKind::Interrupted
+    )
+}
@@ -1,4 +1,8 @@
+use crate::config::ConfigDbType;
+use crate::error::{NativeError, NativeErrorCode};
+use crate::schema::SchemaRows;
 use crate::traits::sync::{Migrate, Query, Transaction};
+use crate::util::StoredVersion;
 use crate::Migration;
 use mysql::{
     error::Error as MError, prelude::Queryable, Conn, IsolationLevel, PooledConn,
@@ -7,6 +11,28 @@ use mysql::{
 use time::format_description::well_known::Rfc3339;
 use time::OffsetDateTime;
 
+// Get the SQLSTATE code and errno the server reported for an error, and where it was for syntax errors
+pub(crate) fn native_error(err: &MError) -> Option<NativeError> {
+    match err {
+        MError::MySqlError(err) => Some((
+            NativeErrorCode {
+                sqlstate: Some(err.state.clone()),
+                number: Some(err.code.into()),
+            },
+            super::mysql_reported_position(err.code, &err.message),
+        )),
+        _ => None,
+    }
+}
+
+pub(crate) fn is_transient(err: &MError) -> bool {
+    match err {
+        MError::IoError(err) => super::is_transient_io(err),
+        MError::MySqlError(err) => super::is_transient_mysql_error(err.code),
+        _ => false,
+    }
+}
+
 fn get_tx_opts() -> TxOpts {
     TxOpts::default()
         .set_with_consistent_snapshot(true)
@@ -22,13 +48,13 @@ fn query_applied_migrations(
     let mut applied = Vec::new();
     for row in rows {
         let row = row?;
-        let version = row.get(0).unwrap();
+        let version: StoredVersion = row.get(0).unwrap();
         let applied_on: String = row.get(2).unwrap();
         // Safe to call unwrap, as we stored it in RFC3339 format on the database
         let applied_on = OffsetDateTime::parse(&applied_on, &Rfc3339).unwrap();
         let checksum: String = row.get(3).unwrap();
 
-        applied.push(Migration::applied(
+        applied.push(Migration::from_history(
             version,
             row.get(1).unwrap(),
             applied_on,
@@ -56,6 +82,18 @@ impl Transaction for Conn {
         transaction.commit()?;
         Ok(count as usize)
     }
+
+    fn execute_outside_transaction<S: AsRef<str>, T: Iterator<Item = S>>(
+        &mut self,
+        queries: T,
+    ) -> Result<usize, Self::Error> {
+        let mut count = 0;
+        for query in queries {
+            self.query_iter(query)?;
+            count += 1;
+        }
+        Ok(count)
+    }
 }
 
 impl Transaction for PooledConn {
@@ -75,6 +113,18 @@ impl Transaction for PooledConn {
         transaction.commit()?;
         Ok(count as usize)
     }
+
+    fn execute_outside_transaction<S: AsRef<str>, T: Iterator<Item = S>>(
+        &mut self,
+        queries: T,
+    ) -> Result<usize, Self::Error> {
+        let mut count = 0;
+        for query in queries {
+            self.query_iter(query)?;
+            count += 1;
+        }
+        Ok(count)
+    }
 }
 
 impl Query<Vec<Migration>> for Conn {
@@ -95,5 +145,38 @@ impl Query<Vec<Migration>> for PooledConn {
     }
 }
 
-impl Migrate for Conn {}
-impl Migrate for PooledConn {}
+fn query_schema_rows<Q: Queryable>(connection: &mut Q, query: &str) -> Result<SchemaRows, MError> {
+    let rows: Vec<mysql::Row> = connection.query(query)?;
+    Ok(rows
+        .into_iter()
+        .map(|row| {
+            (0..row.len())
+                .map(|index| row.get(index).unwrap())
+                .collect()
+        })
+        .collect())
+}
+
+impl Query<SchemaRows> for Conn {
+    fn query(&mut self, query: &str) -> Result<SchemaRows, Self::Error> {
+        query_schema_rows(self, query)
+    }
+}
+
+impl Query<SchemaRows> for PooledConn {
+    fn query(&mut self, query: &str) -> Result<SchemaRows, Self::Error> {
+        query_schema_rows(self, query)
+    }
+}
+
+impl Migrate for Conn {
+    fn dialect(&self) -> Option<ConfigDbType> {
+        Some(ConfigDbType::Mysql)
+    }
+}
+
+impl Migrate for PooledConn {
+    fn dialect(&self) -> Option<ConfigDbType> {
+        Some(ConfigDbType::Mysql)
+    }
+}
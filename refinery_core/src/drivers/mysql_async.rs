@@ -1,5 +1,8 @@
+use crate::config::ConfigDbType;
+use crate::error::{NativeError, NativeErrorCode};
+use crate::schema::SchemaRows;
 use crate::traits::r#async::{AsyncMigrate, AsyncQuery, AsyncTransaction};
-use crate::util::SchemaVersion;
+use crate::util::StoredVersion;
 use crate::Migration;
 use async_trait::async_trait;
 use mysql_async::{
@@ -8,6 +11,28 @@ use mysql_async::{
 use time::format_description::well_known::Rfc3339;
 use time::OffsetDateTime;
 
+// Get the SQLSTATE code and errno the server reported for an error, and where it was for syntax errors
+pub(crate) fn native_error(err: &MError) -> Option<NativeError> {
+    match err {
+        MError::Server(err) => Some((
+            NativeErrorCode {
+                sqlstate: Some(err.state.clone()),
+                number: Some(err.code.into()),
+            },
+            super::mysql_reported_position(err.code, &err.message),
+        )),
+        _ => None,
+    }
+}
+
+pub(crate) fn is_transient(err: &MError) -> bool {
+    match err {
+        MError::Io(mysql_async::IoError::Io(err)) => super::is_transient_io(err),
+        MError::Server(err) => super::is_transient_mysql_error(err.code),
+        _ => false,
+    }
+}
+
 async fn query_applied_migrations<'a>(
     mut transaction: MTransaction<'a>,
     query: &str,
@@ -17,12 +42,12 @@ async fn query_applied_migrations<'a>(
     let applied = result
         .into_iter()
         .map(|row| {
-            let (version, name, applied_on, checksum): (SchemaVersion, String, String, String) =
+            let (version, name, applied_on, checksum): (StoredVersion, String, String, String) =
                 mysql_async::from_row(row);
 
             // Safe to call unwrap, as we stored it in RFC3339 format on the database
             let applied_on = OffsetDateTime::parse(&applied_on, &Rfc3339).unwrap();
-            Migration::applied(
+            Migration::from_history(
                 version,
                 name,
                 applied_on,
@@ -57,6 +82,19 @@ impl AsyncTransaction for Pool {
         transaction.commit().await?;
         Ok(count as usize)
     }
+
+    async fn execute_outside_transaction<S: AsRef<str> + Send, T: Iterator<Item = S> + Send>(
+        &mut self,
+        queries: T,
+    ) -> Result<usize, Self::Error> {
+        let mut conn = self.get_conn().await?;
+        let mut count = 0;
+        for query in queries {
+            conn.query_drop(query.as_ref()).await?;
+            count += 1;
+        }
+        Ok(count)
+    }
 }
 
 #[async_trait]
@@ -76,4 +114,24 @@ impl AsyncQuery<Vec<Migration>> for Pool {
     }
 }
 
-impl AsyncMigrate for Pool {}
+#[async_trait]
+impl AsyncQuery<SchemaRows> for Pool {
+    async fn query(&mut self, query: &str) -> Result<SchemaRows, <Self as AsyncTransaction>::Error> {
+        let mut conn = self.get_conn().await?;
+        let rows: Vec<mysql_async::Row> = conn.query(query).await?;
+        Ok(rows
+            .into_iter()
+            .map(|row| {
+                (0..row.len())
+                    .map(|index| row.get(index).unwrap())
+                    .collect()
+            })
+            .collect())
+    }
+}
+
+impl AsyncMigrate for Pool {
+    fn dialect(&self) -> Option<ConfigDbType> {
+        Some(ConfigDbType::Mysql)
+    }
+}
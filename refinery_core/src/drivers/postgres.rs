@@ -1,9 +1,58 @@
+use crate::config::ConfigDbType;
+use crate::error::{NativeError, NativeErrorCode, ReportedPosition};
+use crate::schema::SchemaRows;
 use crate::traits::sync::{Migrate, Query, Transaction};
+use crate::util::StoredVersion;
 use crate::Migration;
-use postgres::{Client as PgClient, Error as PgError, Transaction as PgTransaction};
+use postgres::error::ErrorPosition;
+#[cfg(feature = "dotted-versions")]
+use postgres::types::Type;
+use postgres::{Client as PgClient, Error as PgError, Row, Transaction as PgTransaction};
 use time::format_description::well_known::Rfc3339;
 use time::OffsetDateTime;
 
+// Get the SQLSTATE code and the position in the executed query the server reported for an error
+pub(crate) fn native_error(err: &PgError) -> Option<NativeError> {
+    let db_error = err.as_db_error()?;
+    let position = match db_error.position() {
+        Some(ErrorPosition::Original(position)) => {
+            Some(ReportedPosition::Character(*position as usize))
+        }
+        _ => None,
+    };
+    Some((
+        NativeErrorCode {
+            sqlstate: Some(db_error.code().code().to_string()),
+            number: None,
+        },
+        position,
+    ))
+}
+
+pub(crate) fn is_transient(err: &PgError) -> bool {
+    match err.as_db_error() {
+        Some(db_error) => super::is_transient_sqlstate(db_error.code().code()),
+        None => {
+            err.is_closed()
+                || std::error::Error::source(err)
+                    .and_then(|source| source.downcast_ref())
+                    .is_some_and(super::is_transient_io)
+        }
+    }
+}
+
+// Read the version of an applied migration. With dotted-versions versions are stored as text, read the integers
+// of a history table created without the feature as text too, instead of failing to convert them
+fn read_version(row: &Row) -> Result<StoredVersion, PgError> {
+    #[cfg(feature = "dotted-versions")]
+    match *row.columns()[0].type_() {
+        Type::INT4 => return row.try_get::<_, i32>(0).map(|version| version.to_string()),
+        Type::INT8 => return row.try_get::<_, i64>(0).map(|version| version.to_string()),
+        _ => {}
+    }
+    row.try_get(0)
+}
+
 fn query_applied_migrations(
     transaction: &mut PgTransaction,
     query: &str,
@@ -11,14 +60,14 @@ fn query_applied_migrations(
     let rows = transaction.query(query, &[])?;
     let mut applied = Vec::new();
     for row in rows.into_iter() {
-        let version = row.get(0);
+        let version = read_version(&row)?;
         let applied_on: String = row.get(2);
         // Safe to call unwrap, as we stored it in RFC3339 format on the database
         let applied_on = OffsetDateTime::parse(&applied_on, &Rfc3339).unwrap();
 
         let checksum: String = row.get(3);
 
-        applied.push(Migration::applied(
+        applied.push(Migration::from_history(
             version,
             row.get(1),
             applied_on,
@@ -46,6 +95,18 @@ impl Transaction for PgClient {
         transaction.commit()?;
         Ok(count as usize)
     }
+
+    fn execute_outside_transaction<S: AsRef<str>, T: Iterator<Item = S>>(
+        &mut self,
+        queries: T,
+    ) -> Result<usize, Self::Error> {
+        let mut count = 0;
+        for query in queries {
+            PgClient::batch_execute(self, query.as_ref())?;
+            count += 1;
+        }
+        Ok(count)
+    }
 }
 
 impl Query<Vec<Migration>> for PgClient {
@@ -57,4 +118,18 @@ impl Query<Vec<Migration>> for PgClient {
     }
 }
 
-impl Migrate for PgClient {}
+impl Query<SchemaRows> for PgClient {
+    fn query(&mut self, query: &str) -> Result<SchemaRows, Self::Error> {
+        let rows = PgClient::query(self, query, &[])?;
+        Ok(rows
+            .into_iter()
+            .map(|row| (0..row.len()).map(|index| row.get(index)).collect())
+            .collect())
+    }
+}
+
+impl Migrate for PgClient {
+    fn dialect(&self) -> Option<ConfigDbType> {
+        Some(ConfigDbType::Postgres)
+    }
+}
@@ -0,0 +1,207 @@
+use crate::config::ConfigDbType;
+use crate::error::WrapMigrationError;
+use crate::schema::{Schema, SchemaRows};
+use crate::traits::sync::{Migrate, Query, Transaction};
+use crate::{Error, Migration, Report, Target};
+use r2d2::{ManageConnection, Pool, PooledConnection};
+
+// a pooled connection migrates as the connection it wraps
+impl<M> Transaction for PooledConnection<M>
+where
+    M: ManageConnection,
+    M::Connection: Transaction,
+{
+    type Error = <M::Connection as Transaction>::Error;
+
+    fn execute<'a, S: AsRef<str>, T: Iterator<Item = S>>(
+        &mut self,
+        queries: T,
+    ) -> Result<usize, Self::Error> {
+        (**self).execute(queries)
+    }
+
+    fn execute_outside_transaction<S: AsRef<str>, T: Iterator<Item = S>>(
+        &mut self,
+        queries: T,
+    ) -> Result<usize, Self::Error> {
+        (**self).execute_outside_transaction(queries)
+    }
+}
+
+impl<M, T> Query<T> for PooledConnection<M>
+where
+    M: ManageConnection,
+    M::Connection: Query<T>,
+{
+    fn query(&mut self, query: &str) -> Result<T, Self::Error> {
+        (**self).query(query)
+    }
+}
+
+impl<M> Migrate for PooledConnection<M>
+where
+    M: ManageConnection,
+    M::Connection: Migrate,
+{
+    fn assert_migrations_table_query(migration_table_name: &str) -> String {
+        M::Connection::assert_migrations_table_query(migration_table_name)
+    }
+
+    fn get_last_applied_migration_query(migration_table_name: &str) -> String {
+        M::Connection::get_last_applied_migration_query(migration_table_name)
+    }
+
+    fn get_applied_migrations_query(migration_table_name: &str) -> String {
+        M::Connection::get_applied_migrations_query(migration_table_name)
+    }
+
+    fn dialect(&self) -> Option<ConfigDbType> {
+        (**self).dialect()
+    }
+}
+
+fn checkout<M: ManageConnection>(pool: &Pool<M>) -> Result<PooledConnection<M>, Error> {
+    pool.get().migration_err(
+        || "could not get a connection from the pool",
+        || [].into_iter(),
+    )
+}
+
+// the pool checks out a connection for every query, and a single one for every migration cycle
+impl<M> Transaction for Pool<M>
+where
+    M: ManageConnection,
+    M::Connection: Transaction,
+{
+    type Error = Error;
+
+    fn execute<'a, S: AsRef<str>, T: Iterator<Item = S>>(
+        &mut self,
+        queries: T,
+    ) -> Result<usize, Self::Error> {
+        checkout(self)?
+            .execute(queries)
+            .migration_err(|| "error executing queries", || [].into_iter())
+    }
+
+    fn execute_outside_transaction<S: AsRef<str>, T: Iterator<Item = S>>(
+        &mut self,
+        queries: T,
+    ) -> Result<usize, Self::Error> {
+        checkout(self)?
+            .execute_outside_transaction(queries)
+            .migration_err(|| "error executing queries", || [].into_iter())
+    }
+}
+
+impl<M, T> Query<T> for Pool<M>
+where
+    M: ManageConnection,
+    M::Connection: Query<T>,
+{
+    fn query(&mut self, query: &str) -> Result<T, Self::Error> {
+        checkout(self)?
+            .query(query)
+            .migration_err(|| "error running query", || [].into_iter())
+    }
+}
+
+impl<M> Migrate for Pool<M>
+where
+    M: ManageConnection,
+    M::Connection: Migrate + Query<SchemaRows>,
+{
+    fn assert_migrations_table_query(migration_table_name: &str) -> String {
+        M::Connection::assert_migrations_table_query(migration_table_name)
+    }
+
+    fn get_last_applied_migration_query(migration_table_name: &str) -> String {
+        M::Connection::get_last_applied_migration_query(migration_table_name)
+    }
+
+    fn get_applied_migrations_query(migration_table_name: &str) -> String {
+        M::Connection::get_applied_migrations_query(migration_table_name)
+    }
+
+    fn dialect(&self) -> Option<ConfigDbType> {
+        checkout(self).ok()?.dialect()
+    }
+
+    fn assert_migrations_table(&mut self, migration_table_name: &str) -> Result<usize, Error> {
+        checkout(self)?.assert_migrations_table(migration_table_name)
+    }
+
+    fn introspect_schema(&mut self, migration_table_name: &str) -> Result<Schema, Error>
+    where
+        Self: Query<SchemaRows>,
+    {
+        checkout(self)?.introspect_schema(migration_table_name)
+    }
+
+    #[cfg(feature = "toml")]
+    fn record_schema_snapshot(&mut self, migration_table_name: &str) -> Result<Schema, Error>
+    where
+        Self: Query<SchemaRows>,
+    {
+        checkout(self)?.record_schema_snapshot(migration_table_name)
+    }
+
+    #[cfg(feature = "toml")]
+    fn get_schema_snapshot(
+        &mut self,
+        migration_table_name: &str,
+    ) -> Result<Option<(Option<String>, Schema)>, Error>
+    where
+        Self: Query<SchemaRows>,
+    {
+        checkout(self)?.get_schema_snapshot(migration_table_name)
+    }
+
+    fn get_last_applied_migration(
+        &mut self,
+        migration_table_name: &str,
+    ) -> Result<Option<Migration>, Error> {
+        checkout(self)?.get_last_applied_migration(migration_table_name)
+    }
+
+    fn get_applied_migrations(
+        &mut self,
+        migration_table_name: &str,
+    ) -> Result<Vec<Migration>, Error> {
+        checkout(self)?.get_applied_migrations(migration_table_name)
+    }
+
+    fn get_unapplied_migrations(
+        &mut self,
+        migrations: &[Migration],
+        abort_divergent: bool,
+        abort_missing: bool,
+        migration_table_name: &str,
+    ) -> Result<Vec<Migration>, Error> {
+        checkout(self)?.get_unapplied_migrations(
+            migrations,
+            abort_divergent,
+            abort_missing,
+            migration_table_name,
+        )
+    }
+
+    fn migrate(
+        &mut self,
+        migrations: &[Migration],
+        abort_divergent: bool,
+        abort_missing: bool,
+        grouped: bool,
+        target: Target,
+        migration_table_name: &str,
+    ) -> Result<Report, Error> {
+        checkout(self)?.migrate(
+            migrations,
+            abort_divergent,
+            abort_missing,
+            grouped,
+            target,
+            migration_table_name,
+        )
+    }
+}
@@ -1,9 +1,68 @@
+use crate::config::ConfigDbType;
+use crate::error::{NativeError, NativeErrorCode, ReportedPosition};
+use crate::schema::SchemaRows;
 use crate::traits::sync::{Migrate, Query, Transaction};
+use crate::util::StoredVersion;
 use crate::Migration;
-use rusqlite::{Connection as RqlConnection, Error as RqlError};
+use rusqlite::fallible_iterator::FallibleIterator;
+use rusqlite::{Batch, Connection as RqlConnection, Error as RqlError};
 use time::format_description::well_known::Rfc3339;
 use time::OffsetDateTime;
 
+// Suffix of the message of an error, naming the statement that failed, sqlite itself doesn't report positions
+const IN_STATEMENT: &str = ", in statement ";
+
+// Get the extended result code sqlite reported for an error, and the statement it was reported for
+pub(crate) fn native_error(err: &RqlError) -> Option<NativeError> {
+    match err {
+        RqlError::SqliteFailure(err, msg) => Some((
+            NativeErrorCode {
+                sqlstate: None,
+                number: Some(err.extended_code.into()),
+            },
+            msg.as_deref()
+                .and_then(|msg| msg.rsplit_once(IN_STATEMENT))
+                .and_then(|(_, statement)| statement.parse().ok())
+                .map(ReportedPosition::Statement),
+        )),
+        _ => None,
+    }
+}
+
+// Execute the statements of `sql` one by one, as `execute_batch` does,
+// naming the failing statement in the message of the error
+fn execute_statements(conn: &RqlConnection, sql: &str) -> Result<(), RqlError> {
+    let mut batch = Batch::new(conn, sql);
+    let mut statement = 0;
+    loop {
+        statement += 1;
+        let result = match batch.next() {
+            // some statements, i.e. PRAGMAs, return rows
+            Ok(Some(mut stmt)) => stmt.query([]).and_then(|mut rows| rows.next().map(|_| ())),
+            Ok(None) => return Ok(()),
+            Err(err) => Err(err),
+        };
+        result.map_err(|err| match err {
+            RqlError::SqliteFailure(err, msg) => {
+                let msg = msg.unwrap_or_else(|| err.to_string());
+                RqlError::SqliteFailure(err, Some(format!("{msg}{IN_STATEMENT}{statement}")))
+            }
+            err => err,
+        })?;
+    }
+}
+
+// Sqlite reports a database locked by another connection as busy or locked
+pub(crate) fn is_transient(err: &RqlError) -> bool {
+    match err {
+        RqlError::SqliteFailure(err, _) => matches!(
+            err.code,
+            rusqlite::ErrorCode::DatabaseBusy | rusqlite::ErrorCode::DatabaseLocked
+        ),
+        _ => false,
+    }
+}
+
 fn query_applied_migrations(
     transaction: &RqlConnection,
     query: &str,
@@ -12,13 +71,13 @@ fn query_applied_migrations(
     let mut rows = stmt.query([])?;
     let mut applied = Vec::new();
     while let Some(row) = rows.next()? {
-        let version = row.get(0)?;
+        let version: StoredVersion = row.get(0)?;
         let applied_on: String = row.get(2)?;
         // Safe to call unwrap, as we stored it in RFC3339 format on the database
         let applied_on = OffsetDateTime::parse(&applied_on, &Rfc3339).unwrap();
 
         let checksum: String = row.get(3)?;
-        applied.push(Migration::applied(
+        applied.push(Migration::from_history(
             version,
             row.get(1)?,
             applied_on,
@@ -39,12 +98,24 @@ impl Transaction for RqlConnection {
         let transaction = self.transaction()?;
         let mut count = 0;
         for query in queries {
-            transaction.execute_batch(query.as_ref())?;
+            execute_statements(&transaction, query.as_ref())?;
             count += 1;
         }
         transaction.commit()?;
         Ok(count)
     }
+
+    fn execute_outside_transaction<S: AsRef<str>, T: Iterator<Item = S>>(
+        &mut self,
+        queries: T,
+    ) -> Result<usize, Self::Error> {
+        let mut count = 0;
+        for query in queries {
+            execute_statements(self, query.as_ref())?;
+            count += 1;
+        }
+        Ok(count)
+    }
 }
 
 impl Query<Vec<Migration>> for RqlConnection {
@@ -56,4 +127,21 @@ impl Query<Vec<Migration>> for RqlConnection {
     }
 }
 
-impl Migrate for RqlConnection {}
+impl Query<SchemaRows> for RqlConnection {
+    fn query(&mut self, query: &str) -> Result<SchemaRows, Self::Error> {
+        let mut stmt = self.prepare(query)?;
+        let column_count = stmt.column_count();
+        let rows = stmt.query_map([], |row| {
+            (0..column_count)
+                .map(|index| row.get(index))
+                .collect::<Result<Vec<_>, _>>()
+        })?;
+        rows.collect()
+    }
+}
+
+impl Migrate for RqlConnection {
+    fn dialect(&self) -> Option<ConfigDbType> {
+        Some(ConfigDbType::Sqlite)
+    }
+}
@@ -0,0 +1,99 @@
+use crate::config::ConfigDbType;
+use crate::schema::SchemaRows;
+use crate::traits::r#async::{AsyncMigrate, AsyncQuery, AsyncTransaction};
+use crate::traits::sync::{Query, Transaction};
+use crate::Migration;
+use async_trait::async_trait;
+use rusqlite::{Connection as RqlConnection, Error as RqlError};
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// A rusqlite connection that migrates asynchronously, running its blocking calls on tokio's blocking thread pool.
+#[derive(Clone, Debug)]
+pub struct AsyncRusqliteConnection(Arc<Mutex<RqlConnection>>);
+
+impl AsyncRusqliteConnection {
+    pub fn new(conn: RqlConnection) -> AsyncRusqliteConnection {
+        AsyncRusqliteConnection(Arc::new(Mutex::new(conn)))
+    }
+
+    async fn run<T, F>(&self, op: F) -> Result<T, RqlError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut RqlConnection) -> Result<T, RqlError> + Send + 'static,
+    {
+        let conn = self.0.clone();
+        let result = tokio::task::spawn_blocking(move || {
+            // a panic while migrating drops its rusqlite transaction, which rolls it back
+            let mut conn = conn.lock().unwrap_or_else(PoisonError::into_inner);
+            op(&mut conn)
+        })
+        .await;
+        match result {
+            Ok(result) => result,
+            Err(err) => std::panic::resume_unwind(err.into_panic()),
+        }
+    }
+}
+
+impl From<RqlConnection> for AsyncRusqliteConnection {
+    fn from(conn: RqlConnection) -> AsyncRusqliteConnection {
+        AsyncRusqliteConnection::new(conn)
+    }
+}
+
+#[async_trait]
+impl AsyncTransaction for AsyncRusqliteConnection {
+    type Error = RqlError;
+
+    async fn execute<'a, S: AsRef<str> + Send, T: Iterator<Item = S> + Send>(
+        &mut self,
+        queries: T,
+    ) -> Result<usize, Self::Error> {
+        let queries = queries
+            .map(|query| query.as_ref().to_string())
+            .collect::<Vec<_>>();
+        self.run(move |conn| conn.execute(queries.into_iter()))
+            .await
+    }
+
+    async fn execute_outside_transaction<S: AsRef<str> + Send, T: Iterator<Item = S> + Send>(
+        &mut self,
+        queries: T,
+    ) -> Result<usize, Self::Error> {
+        let queries = queries
+            .map(|query| query.as_ref().to_string())
+            .collect::<Vec<_>>();
+        self.run(move |conn| conn.execute_outside_transaction(queries.into_iter()))
+            .await
+    }
+}
+
+#[async_trait]
+impl AsyncQuery<Vec<Migration>> for AsyncRusqliteConnection {
+    async fn query(
+        &mut self,
+        query: &str,
+    ) -> Result<Vec<Migration>, <Self as AsyncTransaction>::Error> {
+        let query = query.to_string();
+        self.run(move |conn| Query::<Vec<Migration>>::query(conn, &query))
+            .await
+    }
+}
+
+#[async_trait]
+impl AsyncQuery<SchemaRows> for AsyncRusqliteConnection {
+    async fn query(
+        &mut self,
+        query: &str,
+    ) -> Result<SchemaRows, <Self as AsyncTransaction>::Error> {
+        let query = query.to_string();
+        self.run(move |conn| Query::<SchemaRows>::query(conn, &query))
+            .await
+    }
+}
+
+impl AsyncMigrate for AsyncRusqliteConnection {
+    fn dialect(&self) -> Option<ConfigDbType> {
+        Some(ConfigDbType::Sqlite)
+    }
+}
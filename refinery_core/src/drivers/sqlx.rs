@@ -0,0 +1,314 @@
+use crate::config::ConfigDbType;
+use crate::error::{NativeError, NativeErrorCode, ReportedPosition, WrapMigrationError};
+use crate::schema::{Schema, SchemaRows};
+use crate::traits::r#async::{AsyncMigrate, AsyncQuery, AsyncTransaction};
+use crate::util::StoredVersion;
+use crate::{Error, Migration, Report, Target};
+use async_trait::async_trait;
+use sqlx::error::DatabaseError;
+use sqlx::mysql::{MySql, MySqlConnection, MySqlDatabaseError};
+use sqlx::postgres::{PgConnection, PgDatabaseError, PgErrorPosition, Postgres};
+use sqlx::sqlite::{Sqlite, SqliteConnection, SqliteError};
+use sqlx::{raw_sql, AssertSqlSafe, Connection, Database, Error as SqlxError, Pool, Row};
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+pub(crate) fn native_error(err: &SqlxError) -> Option<NativeError> {
+    let SqlxError::Database(err) = err else {
+        return None;
+    };
+    if let Some(err) = err.try_downcast_ref::<PgDatabaseError>() {
+        let position = match err.position() {
+            Some(PgErrorPosition::Original(position)) => {
+                Some(ReportedPosition::Character(position))
+            }
+            _ => None,
+        };
+        return Some((
+            NativeErrorCode {
+                sqlstate: Some(err.code().to_string()),
+                number: None,
+            },
+            position,
+        ));
+    }
+    if let Some(err) = err.try_downcast_ref::<MySqlDatabaseError>() {
+        return Some((
+            NativeErrorCode {
+                sqlstate: err.code().map(str::to_string),
+                number: Some(err.number().into()),
+            },
+            super::mysql_reported_position(err.number(), err.message()),
+        ));
+    }
+    if let Some(err) = err.try_downcast_ref::<SqliteError>() {
+        // sqlx reports the extended result code as the error code
+        return Some((
+            NativeErrorCode {
+                sqlstate: None,
+                number: err.code().and_then(|code| code.parse().ok()),
+            },
+            None,
+        ));
+    }
+    None
+}
+
+pub(crate) fn is_transient(err: &SqlxError) -> bool {
+    match err {
+        SqlxError::Io(err) => super::is_transient_io(err),
+        SqlxError::PoolTimedOut => true,
+        SqlxError::Database(err) => {
+            if let Some(err) = err.try_downcast_ref::<PgDatabaseError>() {
+                super::is_transient_sqlstate(err.code())
+            } else if let Some(err) = err.try_downcast_ref::<MySqlDatabaseError>() {
+                super::is_transient_mysql_error(err.number())
+            } else {
+                // Sqlite reports a database locked by another connection as busy (5) or locked (6),
+                // the primary result code being the lower byte of the extended one
+                err.try_downcast_ref::<SqliteError>()
+                    .and_then(|err| err.code()?.parse::<i32>().ok())
+                    .is_some_and(|code| matches!(code & 0xff, 5 | 6))
+            }
+        }
+        _ => false,
+    }
+}
+
+// sqlx connections and pools share their api, written as macro so that we don't have to deal with its type signatures
+macro_rules! impl_sqlx {
+    ($type: ty, $executor: path) => {
+        #[async_trait]
+        impl AsyncTransaction for $type {
+            type Error = SqlxError;
+
+            async fn execute<'a, S: AsRef<str> + Send, T: Iterator<Item = S> + Send>(
+                &mut self,
+                queries: T,
+            ) -> Result<usize, Self::Error> {
+                let mut transaction = self.begin().await?;
+                let mut count = 0;
+                for query in queries {
+                    raw_sql(AssertSqlSafe(query.as_ref().to_string()))
+                        .execute(&mut *transaction)
+                        .await?;
+                    count += 1;
+                }
+                transaction.commit().await?;
+                Ok(count)
+            }
+
+            async fn execute_outside_transaction<
+                S: AsRef<str> + Send,
+                T: Iterator<Item = S> + Send,
+            >(
+                &mut self,
+                queries: T,
+            ) -> Result<usize, Self::Error> {
+                let mut count = 0;
+                for query in queries {
+                    raw_sql(AssertSqlSafe(query.as_ref().to_string()))
+                        .execute($executor(self))
+                        .await?;
+                    count += 1;
+                }
+                Ok(count)
+            }
+        }
+
+        #[async_trait]
+        impl AsyncQuery<Vec<Migration>> for $type {
+            async fn query(
+                &mut self,
+                query: &str,
+            ) -> Result<Vec<Migration>, <Self as AsyncTransaction>::Error> {
+                let mut transaction = self.begin().await?;
+                let rows = raw_sql(AssertSqlSafe(query.to_string()))
+                    .fetch_all(&mut *transaction)
+                    .await?;
+                transaction.commit().await?;
+
+                rows.iter()
+                    .map(|row| {
+                        let version: StoredVersion = row.try_get_unchecked(0)?;
+                        let applied_on: String = row.try_get_unchecked(2)?;
+                        // Safe to call unwrap, as we stored it in RFC3339 format on the database
+                        let applied_on = OffsetDateTime::parse(&applied_on, &Rfc3339).unwrap();
+                        let checksum: String = row.try_get_unchecked(3)?;
+
+                        Ok(Migration::from_history(
+                            version,
+                            row.try_get_unchecked(1)?,
+                            applied_on,
+                            checksum
+                                .parse::<u64>()
+                                .expect("checksum must be a valid u64"),
+                        ))
+                    })
+                    .collect()
+            }
+        }
+
+        #[async_trait]
+        impl AsyncQuery<SchemaRows> for $type {
+            async fn query(
+                &mut self,
+                query: &str,
+            ) -> Result<SchemaRows, <Self as AsyncTransaction>::Error> {
+                let mut transaction = self.begin().await?;
+                let rows = raw_sql(AssertSqlSafe(query.to_string()))
+                    .fetch_all(&mut *transaction)
+                    .await?;
+                transaction.commit().await?;
+
+                rows.iter()
+                    .map(|row| {
+                        (0..row.len())
+                            .map(|index| row.try_get_unchecked(index))
+                            .collect()
+                    })
+                    .collect()
+            }
+        }
+    };
+}
+
+// Connections execute queries on their own, pools do by reference
+fn connection_executor<C>(connection: &mut C) -> &mut C {
+    connection
+}
+
+fn pool_executor<DB: Database>(pool: &mut Pool<DB>) -> &Pool<DB> {
+    pool
+}
+
+impl_sqlx!(PgConnection, connection_executor);
+impl_sqlx!(MySqlConnection, connection_executor);
+impl_sqlx!(SqliteConnection, connection_executor);
+impl_sqlx!(Pool<Postgres>, pool_executor);
+impl_sqlx!(Pool<MySql>, pool_executor);
+impl_sqlx!(Pool<Sqlite>, pool_executor);
+
+impl AsyncMigrate for PgConnection {
+    fn dialect(&self) -> Option<ConfigDbType> {
+        Some(ConfigDbType::Postgres)
+    }
+}
+
+impl AsyncMigrate for MySqlConnection {
+    fn dialect(&self) -> Option<ConfigDbType> {
+        Some(ConfigDbType::Mysql)
+    }
+}
+
+impl AsyncMigrate for SqliteConnection {
+    fn dialect(&self) -> Option<ConfigDbType> {
+        Some(ConfigDbType::Sqlite)
+    }
+}
+
+// the pool checks out a connection for every query, and a single one for every migration cycle
+macro_rules! impl_sqlx_pool {
+    ($database: ty, $dialect: expr) => {
+        #[async_trait]
+        impl AsyncMigrate for Pool<$database> {
+            fn dialect(&self) -> Option<ConfigDbType> {
+                Some($dialect)
+            }
+
+            async fn introspect_schema(
+                &mut self,
+                migration_table_name: &str,
+            ) -> Result<Schema, Error>
+            where
+                Self: AsyncQuery<SchemaRows>,
+            {
+                let mut conn = self.acquire().await.migration_err(
+                    || "could not get a connection from the pool",
+                    || [].into_iter(),
+                )?;
+                conn.introspect_schema(migration_table_name).await
+            }
+
+            #[cfg(feature = "toml")]
+            async fn record_schema_snapshot(
+                &mut self,
+                migration_table_name: &str,
+            ) -> Result<Schema, Error>
+            where
+                Self: AsyncQuery<SchemaRows>,
+            {
+                let mut conn = self.acquire().await.migration_err(
+                    || "could not get a connection from the pool",
+                    || [].into_iter(),
+                )?;
+                conn.record_schema_snapshot(migration_table_name).await
+            }
+
+            #[cfg(feature = "toml")]
+            async fn get_schema_snapshot(
+                &mut self,
+                migration_table_name: &str,
+            ) -> Result<Option<(Option<String>, Schema)>, Error>
+            where
+                Self: AsyncQuery<SchemaRows>,
+            {
+                let mut conn = self.acquire().await.migration_err(
+                    || "could not get a connection from the pool",
+                    || [].into_iter(),
+                )?;
+                conn.get_schema_snapshot(migration_table_name).await
+            }
+
+            async fn get_last_applied_migration(
+                &mut self,
+                migration_table_name: &str,
+            ) -> Result<Option<Migration>, Error> {
+                let mut conn = self.acquire().await.migration_err(
+                    || "could not get a connection from the pool",
+                    || [].into_iter(),
+                )?;
+                conn.get_last_applied_migration(migration_table_name).await
+            }
+
+            async fn get_applied_migrations(
+                &mut self,
+                migration_table_name: &str,
+            ) -> Result<Vec<Migration>, Error> {
+                let mut conn = self.acquire().await.migration_err(
+                    || "could not get a connection from the pool",
+                    || [].into_iter(),
+                )?;
+                conn.get_applied_migrations(migration_table_name).await
+            }
+
+            async fn migrate(
+                &mut self,
+                migrations: &[Migration],
+                abort_divergent: bool,
+                abort_missing: bool,
+                grouped: bool,
+                target: Target,
+                migration_table_name: &str,
+            ) -> Result<Report, Error> {
+                let mut conn = self.acquire().await.migration_err(
+                    || "could not get a connection from the pool",
+                    || [].into_iter(),
+                )?;
+                conn.migrate(
+                    migrations,
+                    abort_divergent,
+                    abort_missing,
+                    grouped,
+                    target,
+                    migration_table_name,
+                )
+                .await
+            }
+        }
+    };
+}
+
+impl_sqlx_pool!(Postgres, ConfigDbType::Postgres);
+impl_sqlx_pool!(MySql, ConfigDbType::Mysql);
+impl_sqlx_pool!(Sqlite, ConfigDbType::Sqlite);
@@ -1,5 +1,8 @@
+use crate::config::ConfigDbType;
+use crate::error::{NativeError, NativeErrorCode, ReportedPosition};
+use crate::schema::SchemaRows;
 use crate::traits::r#async::{AsyncMigrate, AsyncQuery, AsyncTransaction};
-use crate::util::SchemaVersion;
+use crate::util::StoredVersion;
 use crate::Migration;
 
 use async_trait::async_trait;
@@ -11,6 +14,30 @@ use tiberius::{error::Error, Client, QueryItem};
 use time::format_description::well_known::Rfc3339;
 use time::OffsetDateTime;
 
+// Get the error number and the line of the executed batch the server reported for an error
+pub(crate) fn native_error(err: &Error) -> Option<NativeError> {
+    match err {
+        Error::Server(err) => Some((
+            NativeErrorCode {
+                sqlstate: None,
+                number: Some(err.code().into()),
+            },
+            Some(ReportedPosition::Line(err.line() as usize)),
+        )),
+        _ => None,
+    }
+}
+
+// Mssql reports lost lock conflicts with 1205, and a database that is starting up or unavailable
+// with 4060, 40197, 40501 and 40613
+pub(crate) fn is_transient(err: &Error) -> bool {
+    match err {
+        Error::Io { kind, .. } => super::is_transient_io_kind(*kind),
+        Error::Server(err) => matches!(err.code(), 1205 | 4060 | 40197 | 40501 | 40613),
+        _ => false,
+    }
+}
+
 async fn query_applied_migrations<S: AsyncRead + AsyncWrite + Unpin + Send>(
     client: &mut Client<S>,
     query: &str,
@@ -20,13 +47,16 @@ async fn query_applied_migrations<S: AsyncRead + AsyncWrite + Unpin + Send>(
     // Unfortunately too many unwraps as `Row::get` maps to Option<T> instead of T
     while let Some(item) = rows.try_next().await? {
         if let QueryItem::Row(row) = item {
-            let version = row.get::<SchemaVersion, usize>(0).unwrap();
+            #[cfg(not(feature = "dotted-versions"))]
+            let version: StoredVersion = row.get::<StoredVersion, usize>(0).unwrap();
+            #[cfg(feature = "dotted-versions")]
+            let version: StoredVersion = row.get::<&str, usize>(0).unwrap().to_string();
             let applied_on: &str = row.get::<&str, usize>(2).unwrap();
             // Safe to call unwrap, as we stored it in RFC3339 format on the database
             let applied_on = OffsetDateTime::parse(applied_on, &Rfc3339).unwrap();
             let checksum: String = row.get::<&str, usize>(3).unwrap().to_string();
 
-            applied.push(Migration::applied(
+            applied.push(Migration::from_history(
                 version,
                 row.get::<&str, usize>(1).unwrap().to_string(),
                 applied_on,
@@ -67,6 +97,19 @@ where
         self.simple_query("COMMIT TRAN T1").await?;
         Ok(count as usize)
     }
+
+    async fn execute_outside_transaction<St: AsRef<str> + Send, T: Iterator<Item = St> + Send>(
+        &mut self,
+        queries: T,
+    ) -> Result<usize, Self::Error> {
+        let mut count = 0;
+        for query in queries {
+            // Drop the returning `QueryStream<'a>` to avoid compiler complaning regarding lifetimes
+            self.simple_query(query.as_ref()).await.map(drop)?;
+            count += 1;
+        }
+        Ok(count)
+    }
 }
 
 #[async_trait]
@@ -83,16 +126,47 @@ where
     }
 }
 
+#[async_trait]
+impl<S> AsyncQuery<SchemaRows> for Client<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send,
+{
+    async fn query(&mut self, query: &str) -> Result<SchemaRows, <Self as AsyncTransaction>::Error> {
+        let mut rows = self.simple_query(query).await?;
+        let mut schema_rows = Vec::new();
+        while let Some(item) = rows.try_next().await? {
+            if let QueryItem::Row(row) = item {
+                schema_rows.push(
+                    (0..row.len())
+                        .map(|index| row.get::<&str, usize>(index).map(str::to_string))
+                        .collect(),
+                );
+            }
+        }
+
+        Ok(schema_rows)
+    }
+}
+
 impl<S> AsyncMigrate for Client<S>
 where
     S: AsyncRead + AsyncWrite + Unpin + Send,
 {
+    fn dialect(&self) -> Option<ConfigDbType> {
+        Some(ConfigDbType::Mssql)
+    }
+
     fn assert_migrations_table_query(migration_table_name: &str) -> String {
+        let version_type = if cfg!(feature = "dotted-versions") {
+            "VARCHAR(255)"
+        } else {
+            "INT"
+        };
         format!(
             "IF NOT EXISTS(SELECT 1 FROM sys.Tables WHERE  Name = N'{migration_table_name}')
          BEGIN
            CREATE TABLE {migration_table_name}(
-             version INT PRIMARY KEY,
+             version {version_type} PRIMARY KEY,
              name VARCHAR(255),
              applied_on VARCHAR(255),
              checksum VARCHAR(255));
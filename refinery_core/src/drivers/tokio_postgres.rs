@@ -1,10 +1,58 @@
+use crate::config::ConfigDbType;
+use crate::error::{NativeError, NativeErrorCode, ReportedPosition};
+use crate::schema::SchemaRows;
 use crate::traits::r#async::{AsyncMigrate, AsyncQuery, AsyncTransaction};
+use crate::util::StoredVersion;
 use crate::Migration;
 use async_trait::async_trait;
 use time::format_description::well_known::Rfc3339;
 use time::OffsetDateTime;
-use tokio_postgres::error::Error as PgError;
-use tokio_postgres::{Client, Transaction as PgTransaction};
+use tokio_postgres::error::{Error as PgError, ErrorPosition};
+#[cfg(feature = "dotted-versions")]
+use tokio_postgres::types::Type;
+use tokio_postgres::{Client, Row, Transaction as PgTransaction};
+
+// Get the SQLSTATE code and the position in the executed query the server reported for an error
+pub(crate) fn native_error(err: &PgError) -> Option<NativeError> {
+    let db_error = err.as_db_error()?;
+    let position = match db_error.position() {
+        Some(ErrorPosition::Original(position)) => {
+            Some(ReportedPosition::Character(*position as usize))
+        }
+        _ => None,
+    };
+    Some((
+        NativeErrorCode {
+            sqlstate: Some(db_error.code().code().to_string()),
+            number: None,
+        },
+        position,
+    ))
+}
+
+pub(crate) fn is_transient(err: &PgError) -> bool {
+    match err.as_db_error() {
+        Some(db_error) => super::is_transient_sqlstate(db_error.code().code()),
+        None => {
+            err.is_closed()
+                || std::error::Error::source(err)
+                    .and_then(|source| source.downcast_ref())
+                    .is_some_and(super::is_transient_io)
+        }
+    }
+}
+
+// Read the version of an applied migration. With dotted-versions versions are stored as text, read the integers
+// of a history table created without the feature as text too, instead of failing to convert them
+fn read_version(row: &Row) -> Result<StoredVersion, PgError> {
+    #[cfg(feature = "dotted-versions")]
+    match *row.columns()[0].type_() {
+        Type::INT4 => return row.try_get::<_, i32>(0).map(|version| version.to_string()),
+        Type::INT8 => return row.try_get::<_, i64>(0).map(|version| version.to_string()),
+        _ => {}
+    }
+    row.try_get(0)
+}
 
 async fn query_applied_migrations(
     transaction: &PgTransaction<'_>,
@@ -12,21 +60,21 @@ async fn query_applied_migrations(
 ) -> Result<Vec<Migration>, PgError> {
     let rows = transaction.query(query, &[]).await?;
     let applied = rows.into_iter().map(|row| {
-        let version = row.get(0);
+        let version = read_version(&row)?;
         let applied_on: String = row.get(2);
         // Safe to call unwrap, as we stored it in RFC3339 format on the database
         let applied_on = OffsetDateTime::parse(&applied_on, &Rfc3339).unwrap();
         let checksum: String = row.get(3);
 
-        Migration::applied(
+        Ok(Migration::from_history(
             version,
             row.get(1),
             applied_on,
             checksum
                 .parse::<u64>()
                 .expect("checksum must be a valid u64"),
-        )
-    }).collect::<Vec<_>>();
+        ))
+    }).collect::<Result<Vec<_>, PgError>>()?;
     Ok(applied)
 }
 
@@ -47,6 +95,18 @@ impl AsyncTransaction for Client {
         transaction.commit().await?;
         Ok(count as usize)
     }
+
+    async fn execute_outside_transaction<S: AsRef<str> + Send, T: Iterator<Item = S> + Send>(
+        &mut self,
+        queries: T,
+    ) -> Result<usize, Self::Error> {
+        let mut count = 0;
+        for query in queries {
+            self.batch_execute(query.as_ref()).await?;
+            count += 1;
+        }
+        Ok(count)
+    }
 }
 
 #[async_trait]
@@ -62,4 +122,19 @@ impl AsyncQuery<Vec<Migration>> for Client {
     }
 }
 
-impl AsyncMigrate for Client {}
+#[async_trait]
+impl AsyncQuery<SchemaRows> for Client {
+    async fn query(&mut self, query: &str) -> Result<SchemaRows, <Self as AsyncTransaction>::Error> {
+        let rows = Client::query(self, query, &[]).await?;
+        Ok(rows
+            .into_iter()
+            .map(|row| (0..row.len()).map(|index| row.get(index)).collect())
+            .collect())
+    }
+}
+
+impl AsyncMigrate for Client {
+    fn dialect(&self) -> Option<ConfigDbType> {
+        Some(ConfigDbType::Postgres)
+    }
+}
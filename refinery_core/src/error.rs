@@ -1,4 +1,5 @@
-use crate::{Migration, Report};
+use crate::config::ConfigDbType;
+use crate::{Migration, Report, SchemaVersion};
 use std::fmt;
 use std::path::PathBuf;
 use thiserror::Error as TError;
@@ -8,14 +9,129 @@ use thiserror::Error as TError;
 pub struct Error {
     kind: Box<Kind>,
     report: Option<Report>,
+    native_code: Option<NativeErrorCode>,
+    reported_position: Option<Box<ReportedPosition>>,
+    position: Option<StatementPosition>,
 }
 
+/// The error code a database reported for a failing statement
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NativeErrorCode {
+    /// The SQLSTATE code, reported by Postgres and Mysql
+    pub sqlstate: Option<String>,
+    /// The vendor error number, reported by Mysql (errno), Mssql and Sqlite (extended result code)
+    pub number: Option<i64>,
+}
+
+/// The position of the failing statement within a migration, as far as the database reports it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StatementPosition {
+    /// The index of the failing statement, starting from 1, statements being separated by semicolons
+    pub statement: usize,
+    /// The line of the migration the error was reported at, starting from 1
+    pub line: usize,
+    /// The column of the migration the error was reported at, starting from 1,
+    /// only when the database reports the exact position, as Postgres does and Mysql does for syntax errors
+    pub column: Option<usize>,
+}
+
+// Where a database reported an error within the executed sql, only constructed by the drivers
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum ReportedPosition {
+    // the character, starting from 1, reported by Postgres
+    #[cfg_attr(
+        not(any(feature = "postgres", feature = "tokio-postgres", feature = "sqlx")),
+        allow(dead_code)
+    )]
+    Character(usize),
+    // the line, starting from 1, reported by Mssql
+    #[cfg_attr(not(feature = "tiberius"), allow(dead_code))]
+    Line(usize),
+    // the sql following the error, cut off by the database, and the line, starting from 1, within the failing
+    // statement, reported by Mysql for syntax errors
+    #[cfg_attr(
+        not(any(feature = "mysql", feature = "mysql_async", feature = "sqlx")),
+        allow(dead_code)
+    )]
+    Near(String, usize),
+    // the index of the failing statement, starting from 1, reported by the rusqlite driver
+    #[cfg_attr(not(feature = "rusqlite"), allow(dead_code))]
+    Statement(usize),
+}
+
+// The error code and position a database reported for an error
+pub(crate) type NativeError = (NativeErrorCode, Option<ReportedPosition>);
+
 impl Error {
     /// Instantiate a new Error
     pub(crate) fn new(kind: Kind, report: Option<Report>) -> Error {
         Error {
             kind: Box::new(kind),
             report,
+            native_code: None,
+            reported_position: None,
+            position: None,
+        }
+    }
+
+    // Locate the position the database reported for the error within the `sql` of the failing migration
+    pub(crate) fn locate(mut self, sql: &str) -> Error {
+        self.position = self
+            .reported_position
+            .as_deref()
+            .and_then(|reported| statement_position(sql, reported));
+        self
+    }
+
+    // Replace a secret, i.e. the database password, with `***` in the message of the error and its source
+    #[cfg(any(
+        feature = "mysql",
+        feature = "postgres",
+        feature = "tokio-postgres",
+        feature = "mysql_async",
+        feature = "sqlx",
+        feature = "tiberius-config"
+    ))]
+    pub(crate) fn redact(mut self, secret: &str) -> Error {
+        if secret.is_empty() {
+            return self;
+        }
+        match self.kind.as_mut() {
+            Kind::ConfigError(msg) => *msg = msg.replace(secret, "***"),
+            Kind::Connection(msg, source) => {
+                *msg = msg.replace(secret, "***");
+                let leaks = std::iter::successors(
+                    Some(source.as_ref() as &(dyn std::error::Error + 'static)),
+                    |err| err.source(),
+                )
+                .any(|err| err.to_string().contains(secret) || format!("{err:?}").contains(secret));
+                if leaks {
+                    *source = Box::new(RedactedError::new(source.as_ref(), secret));
+                }
+            }
+            _ => {}
+        }
+        self
+    }
+
+    /// Return the error code reported by the database if any
+    pub fn native_code(&self) -> Option<&NativeErrorCode> {
+        self.native_code.as_ref()
+    }
+
+    /// Return the position of the failing statement within the migration if the database reported it.
+    /// Positions are only located for migrations run on their own, not when they're run grouped in a single transaction,
+    /// as it's not known which of the grouped migrations failed
+    pub fn position(&self) -> Option<&StatementPosition> {
+        self.position.as_ref()
+    }
+
+    /// Return whether the error is likely to go away when retrying, i.e. the database wasn't reachable yet,
+    /// the connection was lost or a statement lost a lock conflict
+    pub fn is_transient(&self) -> bool {
+        match self.kind.as_ref() {
+            Kind::Connection(_, err) => crate::drivers::is_transient(err.as_ref()),
+            _ => false,
         }
     }
 
@@ -32,8 +148,120 @@ impl Error {
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.kind)
+        write!(f, "{}", self.kind)?;
+        if let Some(position) = &self.position {
+            write!(
+                f,
+                ", at statement {} line {}",
+                position.statement, position.line
+            )?;
+            if let Some(column) = position.column {
+                write!(f, " column {}", column)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// Translates the position reported by the database into the failing statement and its line and column,
+// statements are counted by the semicolons that end them, skipping those within quotes and comments
+fn statement_position(sql: &str, reported: &ReportedPosition) -> Option<StatementPosition> {
+    let (offset, column) = match *reported {
+        ReportedPosition::Character(character) => {
+            let offset = sql
+                .char_indices()
+                .nth(character.checked_sub(1)?)
+                .map(|(offset, _)| offset)?;
+            (offset, Some(column(sql, offset)))
+        }
+        ReportedPosition::Line(line) => (line_start(sql, line)?, None),
+        // the sql following the error runs to the end of the migration unless it's cut off, so look for the last match
+        ReportedPosition::Near(ref near, line) => match sql.rfind(near.as_str()) {
+            Some(offset) if !near.is_empty() => (offset, Some(column(sql, offset))),
+            // Mysql counts the line from the start of the failing statement, which is only known to be
+            // the start of the migration
+            _ => (line_start(sql, line)?, None),
+        },
+        ReportedPosition::Statement(statement) => {
+            let start = match statement.checked_sub(2) {
+                Some(index) => *statement_ends(sql).get(index)? + 1,
+                None => 0,
+            };
+            (start + indent(&sql[start..]), None)
+        }
+    };
+
+    let line = sql[..offset].matches('\n').count() + 1;
+    let statement = statement_ends(&sql[..offset]).len() + 1;
+    Some(StatementPosition {
+        statement,
+        line,
+        column,
+    })
+}
+
+// Get the column, starting from 1, of the character at `offset`
+fn column(sql: &str, offset: usize) -> usize {
+    let line_start = sql[..offset].rfind('\n').map_or(0, |index| index + 1);
+    sql[line_start..offset].chars().count() + 1
+}
+
+// Get the offset of the first character of `line`, where the statement starts
+fn line_start(sql: &str, line: usize) -> Option<usize> {
+    let line_start = if line <= 1 {
+        0
+    } else {
+        sql.match_indices('\n').nth(line - 2)?.0 + 1
+    };
+    Some(line_start + indent(&sql[line_start..]))
+}
+
+// Get the length of the whitespace `sql` starts with
+fn indent(sql: &str) -> usize {
+    sql.find(|c: char| !c.is_whitespace()).unwrap_or(0)
+}
+
+// Get the offsets of the semicolons ending statements in `sql`
+fn statement_ends(sql: &str) -> Vec<usize> {
+    let mut ends = Vec::new();
+    let mut offset = 0;
+    while let Some(c) = sql[offset..].chars().next() {
+        let rest = &sql[offset..];
+        let skip = match c {
+            ';' => {
+                ends.push(offset);
+                1
+            }
+            '\'' | '"' | '`' => closing(rest, c),
+            '[' => closing(rest, ']'),
+            '-' if rest.starts_with("--") => rest.find('\n').unwrap_or(rest.len()),
+            '/' if rest.starts_with("/*") => rest.find("*/").map_or(rest.len(), |end| end + 2),
+            '$' => match dollar_tag(rest) {
+                Some(tag) => rest[tag.len()..]
+                    .find(tag)
+                    .map_or(rest.len(), |end| tag.len() + end + tag.len()),
+                None => 1,
+            },
+            _ => c.len_utf8(),
+        };
+        offset += skip;
     }
+    ends
+}
+
+// Get the length of the quoted string or identifier `sql` starts with, up to and including the closing `quote`,
+// a doubled quote escaping a quote is read as two strings
+fn closing(sql: &str, quote: char) -> usize {
+    match sql[1..].find(quote) {
+        Some(end) => end + 1 + quote.len_utf8(),
+        None => sql.len(),
+    }
+}
+
+// Get the tag starting a Postgres dollar quoted string, i.e. `$$` or `$body$`
+fn dollar_tag(sql: &str) -> Option<&str> {
+    let end = sql[1..].find(|c: char| !(c.is_alphanumeric() || c == '_'))? + 1;
+    sql[end..].starts_with('$').then(|| &sql[..=end])
 }
 
 impl std::error::Error for Error {
@@ -42,30 +270,112 @@ impl std::error::Error for Error {
     }
 }
 
+// The source of a connection error whose message, or the message of one of its sources, contained a secret
+#[cfg(any(
+    feature = "mysql",
+    feature = "postgres",
+    feature = "tokio-postgres",
+    feature = "mysql_async",
+    feature = "sqlx",
+    feature = "tiberius-config"
+))]
+#[derive(Debug, TError)]
+#[error("{message}")]
+struct RedactedError {
+    message: String,
+    #[source]
+    source: Option<Box<RedactedError>>,
+}
+
+#[cfg(any(
+    feature = "mysql",
+    feature = "postgres",
+    feature = "tokio-postgres",
+    feature = "mysql_async",
+    feature = "sqlx",
+    feature = "tiberius-config"
+))]
+impl RedactedError {
+    // Copy `err` and its sources, replacing `secret` with `***` in their messages
+    fn new(err: &(dyn std::error::Error + 'static), secret: &str) -> RedactedError {
+        RedactedError {
+            message: err.to_string().replace(secret, "***"),
+            source: err
+                .source()
+                .map(|source| Box::new(RedactedError::new(source, secret))),
+        }
+    }
+}
+
 /// Enum listing possible errors from Refinery.
 #[derive(Debug, TError)]
 pub enum Kind {
     /// An Error from an invalid file name migration
-    #[error("migration name must be in the format {{number}}(U|V|R)__{{name}}")]
+    #[error("migration name must be in the format {{number}}(U|V|R|B)__{{name}}")]
     InvalidName,
     /// An Error from an invalid version on a file name migration
-    #[error("migration version must be a valid integer")]
+    #[error("migration version must be a valid integer, or dot separated integers with the dotted-versions feature")]
     InvalidVersion,
+    /// An Error from an invalid `migration.toml` metadata file of a directory migration
+    #[error("invalid migration metadata file {0}: {1}")]
+    InvalidMigrationMetadata(PathBuf, String),
+    /// An Error from introspecting the schema through a connection that doesn't know its database dialect
+    #[error("schema introspection requires a connection to a known database")]
+    UnknownDialect,
+    /// An Error from detecting drift on a database without a recorded schema snapshot
+    #[error("no schema snapshot was recorded, record one after migrating to detect drift")]
+    MissingSnapshot,
+    /// An Error from detecting drift with a schema snapshot recorded before the last applied migration
+    #[error("schema snapshot was recorded at version {0} but the database is at version {1}, record a new snapshot after migrating")]
+    OutdatedSnapshot(String, String),
+    /// An Error from a recorded schema snapshot that can't be read
+    #[error("invalid schema snapshot, {0}")]
+    InvalidSnapshot(String),
+    /// An Error from an invalid migration naming pattern
+    #[error("invalid migration naming pattern, {0}")]
+    InvalidNamingPattern(String),
     /// An Error from a repeated version, migration version numbers must be unique
     #[error("migration {0} is repeated, migration versions must be unique")]
     RepeatedVersion(Migration),
+    /// An Error from a migration depending on a migration that can't be found
+    #[error("migration {0} depends on {1}, which can't be found")]
+    MissingDependency(Migration, String),
+    /// An Error from migrations depending on each other in a cycle
+    #[error("migration {0} is part of a dependency cycle")]
+    CyclicDependency(Migration),
+    /// An Error from an applied migration whose dependency isn't applied
+    #[error("migration {0} is applied but its dependency {1} isn't")]
+    UnappliedDependency(Migration, Migration),
     /// An Error from an divergent version, the applied version is different to the filesystem one
     #[error("applied migration {0} is different than filesystem one {1}")]
     DivergentVersion(Migration, Migration),
+    /// An Error from squashing migrations on a database that isn't empty
+    #[error("migrations can only be squashed on an empty scratch database")]
+    NonEmptyScratchDatabase,
+    /// An Error from squashing migrations that create objects a baseline can't hold, i.e. views, triggers or foreign keys
+    #[error("migrations can't be squashed, the baseline would leave out {}", .0.join(", "))]
+    UncapturedSchemaObjects(Vec<String>),
+    /// An Error from a target version that can't tell apart the dot separated versions of a migration
+    #[error("target version {1} can't tell migration {0} apart from the other {1}.x versions, as targets only take the major version")]
+    AmbiguousTarget(Migration, SchemaVersion),
+    /// An Error from a migration that can't run inside a transaction being applied grouped with others
+    #[error("migration {0} can't run inside a transaction, so it can't be applied grouped with other migrations")]
+    NonTransactionalGrouped(Migration),
     /// An Error from an divergent version, the applied version is missing on the filesystem
     #[error("migration {0} is missing from the filesystem")]
     MissingVersion(Migration),
     /// An Error from an invalid migrations path location
     #[error("invalid migrations path {0}, {1}")]
     InvalidMigrationPath(PathBuf, std::io::Error),
+    /// An Error from writing the schema file dumped after migrating
+    #[error("could not write schema file {0}, {1}")]
+    InvalidSchemaFile(PathBuf, std::io::Error),
     /// An Error parsing refinery Config
     #[error("Error parsing config: {0}")]
     ConfigError(String),
+    /// An Error from a Config for a database whose driver feature isn't enabled in this build
+    #[error("{0:?} databases are not supported by this build, enable the {1} feature")]
+    UnsupportedDatabase(ConfigDbType, &'static str),
     /// An Error from an underlying database connection Error
     #[error("`{0}`, `{1}`")]
     Connection(String, #[source] Box<dyn std::error::Error + Sync + Send>),
@@ -98,11 +408,90 @@ where
                 } else {
                     Some(Report::new(reportable))
                 };
+                let native_error = crate::drivers::native_error(&err);
                 Err(Error {
                     kind: Box::new(Kind::Connection(msg().into(), Box::new(err))),
-                    report
+                    report,
+                    native_code: native_error.as_ref().map(|(code, _)| code.clone()),
+                    reported_position: native_error
+                        .and_then(|(_, position)| position.map(Box::new)),
+                    position: None,
                 })}
             ,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{statement_position, ReportedPosition, StatementPosition};
+
+    const SQL: &str = "CREATE TABLE persons (name varchar(255) DEFAULT 'a;b');
+-- a comment; with a semicolon
+CREATE FUNCTION f() RETURNS int AS $body$ BEGIN RETURN 1; END $body$ LANGUAGE plpgsql;
+  INSERT INTO persons (nme) VALUES ('é');";
+
+    #[test]
+    fn locates_statement_from_character() {
+        let character = SQL[..SQL.find("nme").unwrap()].chars().count() + 1;
+        assert_eq!(
+            Some(StatementPosition {
+                statement: 3,
+                line: 4,
+                column: Some(24),
+            }),
+            statement_position(SQL, &ReportedPosition::Character(character))
+        );
+    }
+
+    #[test]
+    fn locates_statement_from_line() {
+        assert_eq!(
+            Some(StatementPosition {
+                statement: 2,
+                line: 3,
+                column: None,
+            }),
+            statement_position(SQL, &ReportedPosition::Line(3))
+        );
+        assert_eq!(None, statement_position(SQL, &ReportedPosition::Line(12)));
+    }
+
+    #[test]
+    fn locates_statement_from_near() {
+        let near = ReportedPosition::Near("(nme) VALUES ('é');".to_string(), 1);
+        assert_eq!(
+            Some(StatementPosition {
+                statement: 3,
+                line: 4,
+                column: Some(23),
+            }),
+            statement_position(SQL, &near)
+        );
+        let end = ReportedPosition::Near(String::new(), 1);
+        assert_eq!(
+            Some(StatementPosition {
+                statement: 1,
+                line: 1,
+                column: None,
+            }),
+            statement_position(SQL, &end)
+        );
+    }
+
+    #[test]
+    fn locates_statement_from_index() {
+        assert_eq!(
+            Some(StatementPosition {
+                statement: 3,
+                line: 4,
+                column: None,
+            }),
+            statement_position(SQL, &ReportedPosition::Statement(3))
+        );
+        assert_eq!(
+            None,
+            statement_position(SQL, &ReportedPosition::Statement(5))
+        );
+    }
+}
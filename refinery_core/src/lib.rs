@@ -2,20 +2,26 @@ pub mod config;
 mod drivers;
 pub mod error;
 mod runner;
+pub mod schema;
 pub mod traits;
 mod util;
 
 pub use crate::error::Error;
-pub use crate::runner::{Migration, Report, Runner, Target, Type};
+pub use crate::runner::{Migration, MigrationMetadata, Report, Runner, Target, Type};
 pub use crate::traits::r#async::AsyncMigrate;
 pub use crate::traits::sync::Migrate;
 pub use crate::util::{
-    find_migration_files, load_sql_migrations, parse_migration_name, MigrationType, SchemaVersion,
+    find_migration_files, find_migration_files_with_naming, load_migration_directory,
+    load_sql_migrations, load_sql_migrations_with_naming, parse_migration_name, MigrationType,
+    NamingStrategy, SchemaVersion,
 };
 
 #[cfg(feature = "rusqlite")]
 pub use rusqlite;
 
+#[cfg(feature = "rusqlite-async")]
+pub use crate::drivers::rusqlite_async::AsyncRusqliteConnection;
+
 #[cfg(feature = "postgres")]
 pub use postgres;
 
@@ -30,3 +36,16 @@ pub use mysql_async;
 
 #[cfg(feature = "tiberius")]
 pub use tiberius;
+
+#[cfg(feature = "r2d2")]
+pub use r2d2;
+
+#[cfg(feature = "sqlx")]
+pub use sqlx;
+
+#[cfg(any(
+    feature = "diesel-postgres",
+    feature = "diesel-mysql",
+    feature = "diesel-sqlite"
+))]
+pub use diesel;
@@ -6,19 +6,34 @@ use std::cmp::Ordering;
 use std::collections::VecDeque;
 use std::fmt;
 use std::hash::{Hash, Hasher};
-
-use crate::traits::{sync::migrate as sync_migrate, DEFAULT_MIGRATION_TABLE_NAME};
-use crate::util::{parse_migration_name, SchemaVersion};
+use std::path::Path;
+
+use crate::config::{Config, ConfigDbType};
+use crate::error::{Kind, WrapMigrationError};
+#[cfg(feature = "toml")]
+use crate::schema::SchemaChange;
+use crate::schema::{Schema, SchemaRows};
+#[cfg(feature = "toml")]
+use crate::traits::detect_drift;
+use crate::traits::{
+    r#async::AsyncQuery,
+    sync::{migrate as sync_migrate, Query},
+    DEFAULT_MIGRATION_TABLE_NAME,
+};
+use crate::util::{default_naming_strategy, MigrationName, NamingStrategy, SchemaVersion};
 use crate::{AsyncMigrate, Error, Migrate};
 use std::fmt::Formatter;
 
 /// An enum set that represents the type of the Migration
 #[derive(Clone, PartialEq)]
+#[non_exhaustive]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Type {
     Versioned,
     Unversioned,
-    Rerunnable
+    Rerunnable,
+    /// A snapshot of the schema created by the migrations up to its version, see [`Runner::squash`]
+    Baseline,
 }
 
 impl fmt::Display for Type {
@@ -27,6 +42,7 @@ impl fmt::Display for Type {
             Type::Versioned => "V",
             Type::Unversioned => "U",
             Type::Rerunnable => "R",
+            Type::Baseline => "B",
         };
         write!(f, "{version_type}")
     }
@@ -38,6 +54,7 @@ impl fmt::Debug for Type {
             Type::Versioned => "Versioned",
             Type::Unversioned => "Unversioned",
             Type::Rerunnable => "Rerunnable",
+            Type::Baseline => "Baseline",
         };
         write!(f, "{version_type}")
     }
@@ -73,16 +90,71 @@ pub struct Migration {
     name: String,
     checksum: u64,
     version: SchemaVersion,
+    #[cfg_attr(feature = "serde", serde(default))]
+    sub_versions: Vec<SchemaVersion>,
     prefix: Type,
+    dialect: Option<ConfigDbType>,
     sql: Option<String>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    down_sql: Option<String>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    metadata: Box<MigrationMetadata>,
     applied_on: Option<OffsetDateTime>,
 }
 
+/// Metadata of a directory migration, read from the `migration.toml` file next to its `up.sql` script
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default, deny_unknown_fields))]
+pub struct MigrationMetadata {
+    /// What the migration does
+    pub description: Option<String>,
+    /// Whether the migration runs inside a transaction, `true` by default.
+    /// Migrations that don't are run outside one, and can't be run grouped with others
+    pub transactional: bool,
+    /// Free form context the migration applies to, i.e. `production` or `test-data`
+    pub context: Option<String>,
+    /// Who wrote the migration
+    pub author: Option<String>,
+    /// Names of the migrations this migration depends on, in the `{1}[U|V|R]__{2}` format
+    pub depends_on: Vec<String>,
+}
+
+impl Default for MigrationMetadata {
+    fn default() -> MigrationMetadata {
+        MigrationMetadata {
+            description: None,
+            transactional: true,
+            context: None,
+            author: None,
+            depends_on: Vec::new(),
+        }
+    }
+}
+
 impl Migration {
     /// Create an unapplied migration, name and version are parsed from the input_name,
     /// which must be named in the format {1}(U|V|R)__{2}.rs where {1} represents the migration version and {2} the name.
+    /// The name may be followed by a database dialect, i.e. {1}(U|V|R)__{2}.postgres.rs, in which case the migration
+    /// is only run on that database, in place of the generic migration with the same version and name.
+    /// With the `dotted-versions` feature {1} can also be a dot separated version, i.e. 2.1.3.
     pub fn unapplied(input_name: &str, sql: &str) -> Result<Migration, Error> {
-        let (prefix, version, name) = parse_migration_name(input_name)?;
+        Migration::unapplied_with_naming(input_name, sql, default_naming_strategy())
+    }
+
+    /// Create an unapplied migration whose name and version are parsed from the input_name according to a [`NamingStrategy`]
+    pub fn unapplied_with_naming(
+        input_name: &str,
+        sql: &str,
+        naming: &NamingStrategy,
+    ) -> Result<Migration, Error> {
+        let MigrationName {
+            prefix,
+            version,
+            sub_versions,
+            name,
+            dialect,
+        } = naming.parse_parts(input_name)?;
 
         // Previously, `std::collections::hash_map::DefaultHasher` was used
         // to calculate the checksum and the implementation at that time
@@ -95,6 +167,10 @@ impl Migration {
         let mut hasher = SipHasher13::new();
         name.hash(&mut hasher);
         version.hash(&mut hasher);
+        // only hashed when present, to keep the checksums of single part versions unchanged
+        if !sub_versions.is_empty() {
+            sub_versions.hash(&mut hasher);
+        }
         sql.hash(&mut hasher);
         let checksum = hasher.finish();
 
@@ -102,8 +178,12 @@ impl Migration {
             state: State::Unapplied,
             name,
             version,
+            sub_versions,
             prefix,
+            dialect,
             sql: Some(sql.into()),
+            down_sql: None,
+            metadata: Box::default(),
             applied_on: None,
             checksum,
         })
@@ -121,13 +201,65 @@ impl Migration {
             name,
             checksum,
             version,
+            sub_versions: Vec::new(),
             // applied migrations are always versioned
             prefix: Type::Versioned,
+            dialect: None,
             sql: None,
+            down_sql: None,
+            metadata: Box::default(),
             applied_on: Some(applied_on),
         }
     }
 
+    // Create a migration from a row of the migrations table
+    #[cfg(any(
+        feature = "mysql",
+        feature = "postgres",
+        feature = "rusqlite",
+        feature = "tokio-postgres",
+        feature = "mysql_async",
+        feature = "tiberius",
+        feature = "sqlx",
+        feature = "diesel-postgres",
+        feature = "diesel-mysql",
+        feature = "diesel-sqlite"
+    ))]
+    pub(crate) fn from_history(
+        version: crate::util::StoredVersion,
+        name: String,
+        applied_on: OffsetDateTime,
+        checksum: u64,
+    ) -> Migration {
+        cfg_if::cfg_if! {
+            if #[cfg(feature = "dotted-versions")] {
+                // Safe to call expect, as we stored it from a parsed version on the database
+                let (version, sub_versions) = crate::util::parse_version(&version)
+                    .expect("version must be a valid dot separated version");
+                Migration {
+                    sub_versions,
+                    ..Migration::applied(version, name, applied_on, checksum)
+                }
+            } else {
+                Migration::applied(version, name, applied_on, checksum)
+            }
+        }
+    }
+
+    /// Set the sql that reverts the Migration, as read from the `down.sql` script of a directory migration.
+    /// It isn't part of the checksum and refinery never runs it
+    pub fn set_down_sql(self, down_sql: Option<String>) -> Migration {
+        Migration { down_sql, ..self }
+    }
+
+    /// Set the Migration metadata, as read from the `migration.toml` file of a directory migration
+    pub fn set_metadata(self, metadata: MigrationMetadata) -> Migration {
+        Migration {
+            metadata: Box::new(metadata),
+            ..self
+        }
+    }
+
     // convert the Unapplied into an Applied Migration
     pub fn set_applied(&mut self) {
         self.applied_on = Some(OffsetDateTime::now_utc());
@@ -139,11 +271,41 @@ impl Migration {
         self.sql.as_deref()
     }
 
-    /// Get the Migration version
+    /// Get the sql that reverts the Migration, `None` when it has no `down.sql` script
+    pub fn down_sql(&self) -> Option<&str> {
+        self.down_sql.as_deref()
+    }
+
+    /// Get the Migration metadata, the defaults when it has no `migration.toml` file
+    pub fn metadata(&self) -> &MigrationMetadata {
+        &self.metadata
+    }
+
+    /// Get the Migration version, for dot separated versions this is the major version
     pub fn version(&self) -> SchemaVersion {
         self.version
     }
 
+    /// Get the versions following the major one of a dot separated version, i.e. `[1, 3]` for `2.1.3`.
+    /// Empty for single part versions
+    pub fn sub_versions(&self) -> &[SchemaVersion] {
+        &self.sub_versions
+    }
+
+    // Get the full version, as stored on the migrations table
+    pub(crate) fn version_str(&self) -> String {
+        std::iter::once(self.version)
+            .chain(self.sub_versions.iter().copied())
+            .map(|part| part.to_string())
+            .collect::<Vec<_>>()
+            .join(".")
+    }
+
+    // Whether both migrations have the same full version
+    pub(crate) fn same_version(&self, other: &Migration) -> bool {
+        self.version == other.version && self.sub_versions == other.sub_versions
+    }
+
     /// Get the Prefix
     pub fn prefix(&self) -> &Type {
         &self.prefix
@@ -154,6 +316,11 @@ impl Migration {
         &self.name
     }
 
+    /// Get the database the Migration was written for. `None` when it applies to any database.
+    pub fn dialect(&self) -> Option<ConfigDbType> {
+        self.dialect
+    }
+
     /// Get the timestamp from when the Migration was applied. `None` when unapplied.
     /// Migrations returned from Runner::get_migrations() will always have `None`.
     pub fn applied_on(&self) -> Option<&OffsetDateTime> {
@@ -168,7 +335,7 @@ impl Migration {
 
 impl fmt::Display for Migration {
     fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(fmt, "{}{}__{}", self.version, self.prefix, self.name)
+        write!(fmt, "{}{}__{}", self.version_str(), self.prefix, self.name)
     }
 }
 
@@ -176,15 +343,15 @@ impl Eq for Migration {}
 
 impl PartialEq for Migration {
     fn eq(&self, other: &Migration) -> bool {
-        self.version == other.version
-            && self.name == other.name
-            && self.checksum() == other.checksum()
+        self.same_version(other) && self.name == other.name && self.checksum() == other.checksum()
     }
 }
 
 impl Ord for Migration {
     fn cmp(&self, other: &Migration) -> Ordering {
-        self.version.cmp(&other.version)
+        self.version
+            .cmp(&other.version)
+            .then_with(|| self.sub_versions.cmp(&other.sub_versions))
     }
 }
 
@@ -247,6 +414,33 @@ impl Runner {
         }
     }
 
+    /// instantiate a new Runner with the settings of the `[migrations]` section of a config file,
+    /// see [MigrationsConfig](crate::config::MigrationsConfig)
+    ///
+    /// # Panics
+    ///
+    /// If the configured `table_name` is empty
+    pub fn from_config(config: &Config, migrations: &[Migration]) -> Runner {
+        let settings = config.migrations();
+        let mut runner = Runner::new(migrations);
+        if let Some(grouped) = settings.grouped {
+            runner.grouped = grouped;
+        }
+        if let Some(abort_divergent) = settings.abort_divergent {
+            runner.abort_divergent = abort_divergent;
+        }
+        if let Some(abort_missing) = settings.abort_missing {
+            runner.abort_missing = abort_missing;
+        }
+        if let Some(version) = settings.target {
+            runner.target = Target::Version(version);
+        }
+        if let Some(table_name) = &settings.table_name {
+            runner.set_migration_table_name(table_name);
+        }
+        runner
+    }
+
     /// Get the gathered migrations.
     pub fn get_migrations(&self) -> &Vec<Migration> {
         &self.migrations
@@ -262,6 +456,8 @@ impl Runner {
 
     /// Set true if all migrations should be grouped and run in a single transaction.
     /// by default this is set to false, each migration runs on their own transaction
+    /// or, for migrations that aren't `transactional`, outside of one.
+    /// Migrations that aren't `transactional` can't be grouped, running fails when any is to be applied.
     ///
     /// # Note
     ///
@@ -396,6 +592,212 @@ impl Runner {
         )
         .await
     }
+
+    /// Squashes the Migrations up to version `up_to` into a baseline Migration named `{up_to}B__baseline`.
+    /// The Migrations are run on the supplied connection, which must be to an empty scratch database,
+    /// otherwise an error is returned, and the resulting schema is introspected to create the baseline.
+    /// A baseline only holds tables, columns, primary keys and indexes on columns, so squashing fails
+    /// when the Migrations create other objects, i.e. views, triggers or foreign keys.
+    /// A baseline is only applied to empty databases, in place of the Migrations it squashes,
+    /// which can then be removed once every database has been migrated past it.
+    pub fn squash<C>(&self, connection: &mut C, up_to: SchemaVersion) -> Result<Migration, Error>
+    where
+        C: Migrate + Query<SchemaRows>,
+    {
+        let dialect = connection
+            .dialect()
+            .ok_or_else(|| Error::new(Kind::UnknownDialect, None))?;
+        // the baseline must only hold the schema created by the squashed migrations
+        connection.assert_migrations_table(&self.migration_table_name)?;
+        if !connection
+            .introspect_schema(&self.migration_table_name)?
+            .tables
+            .is_empty()
+            || !connection
+                .get_applied_migrations(&self.migration_table_name)?
+                .is_empty()
+        {
+            return Err(Error::new(Kind::NonEmptyScratchDatabase, None));
+        }
+        Migrate::migrate(
+            connection,
+            &self.migrations,
+            self.abort_divergent,
+            self.abort_missing,
+            self.grouped,
+            Target::Version(up_to),
+            &self.migration_table_name,
+        )?;
+        let uncaptured = connection.uncaptured_schema_objects()?;
+        if !uncaptured.is_empty() {
+            return Err(Error::new(Kind::UncapturedSchemaObjects(uncaptured), None));
+        }
+        let schema = connection.introspect_schema(&self.migration_table_name)?;
+        Migration::unapplied(&format!("{}B__baseline", up_to), &schema.to_sql(dialect))
+    }
+
+    /// Squashes the Migrations up to version `up_to` into a baseline Migration asynchronously, see [`Runner::squash`]
+    pub async fn squash_async<C>(
+        &self,
+        connection: &mut C,
+        up_to: SchemaVersion,
+    ) -> Result<Migration, Error>
+    where
+        C: AsyncMigrate + AsyncQuery<SchemaRows> + Send,
+    {
+        let dialect = connection
+            .dialect()
+            .ok_or_else(|| Error::new(Kind::UnknownDialect, None))?;
+        // the baseline must only hold the schema created by the squashed migrations
+        connection
+            .execute([C::assert_migrations_table_query(&self.migration_table_name)].into_iter())
+            .await
+            .migration_err(|| "error asserting migrations table", || [].into_iter())?;
+        if !connection
+            .introspect_schema(&self.migration_table_name)
+            .await?
+            .tables
+            .is_empty()
+            || !connection
+                .get_applied_migrations(&self.migration_table_name)
+                .await?
+                .is_empty()
+        {
+            return Err(Error::new(Kind::NonEmptyScratchDatabase, None));
+        }
+        AsyncMigrate::migrate(
+            connection,
+            &self.migrations,
+            self.abort_divergent,
+            self.abort_missing,
+            self.grouped,
+            Target::Version(up_to),
+            &self.migration_table_name,
+        )
+        .await?;
+        let uncaptured = connection.uncaptured_schema_objects().await?;
+        if !uncaptured.is_empty() {
+            return Err(Error::new(Kind::UncapturedSchemaObjects(uncaptured), None));
+        }
+        let schema = connection
+            .introspect_schema(&self.migration_table_name)
+            .await?;
+        Migration::unapplied(&format!("{}B__baseline", up_to), &schema.to_sql(dialect))
+    }
+
+    /// Writes the schema of the supplied database connection to the file at `location`, to be done after running the Migrations.
+    /// The file holds the statements that create the schema, rendered for the database's dialect
+    /// and ordered by name, so that committing it shows the net schema effect of new Migrations
+    pub fn dump_schema<C>(&self, connection: &mut C, location: &Path) -> Result<Schema, Error>
+    where
+        C: Migrate + Query<SchemaRows>,
+    {
+        let dialect = connection
+            .dialect()
+            .ok_or_else(|| Error::new(Kind::UnknownDialect, None))?;
+        let last_applied = connection.get_last_applied_migration(&self.migration_table_name)?;
+        let schema = connection.introspect_schema(&self.migration_table_name)?;
+        write_schema_file(location, &schema, dialect, last_applied)?;
+        Ok(schema)
+    }
+
+    /// Writes the schema of the supplied database connection asynchronously, see [`Runner::dump_schema`]
+    pub async fn dump_schema_async<C>(
+        &self,
+        connection: &mut C,
+        location: &Path,
+    ) -> Result<Schema, Error>
+    where
+        C: AsyncMigrate + AsyncQuery<SchemaRows> + Send,
+    {
+        let dialect = connection
+            .dialect()
+            .ok_or_else(|| Error::new(Kind::UnknownDialect, None))?;
+        let last_applied = connection
+            .get_last_applied_migration(&self.migration_table_name)
+            .await?;
+        let schema = connection
+            .introspect_schema(&self.migration_table_name)
+            .await?;
+        write_schema_file(location, &schema, dialect, last_applied)?;
+        Ok(schema)
+    }
+
+    /// Records a snapshot of the schema of the supplied database connection,
+    /// to be done after running the Migrations so that [`Runner::detect_drift`] can later tell apart
+    /// changes applied by hand. The snapshot is stored next to the migrations table, with a `_snapshot` suffix
+    #[cfg(feature = "toml")]
+    pub fn record_snapshot<C>(&self, connection: &mut C) -> Result<Schema, Error>
+    where
+        C: Migrate + Query<SchemaRows>,
+    {
+        connection.record_schema_snapshot(&self.migration_table_name)
+    }
+
+    /// Records a snapshot of the schema asynchronously, see [`Runner::record_snapshot`]
+    #[cfg(feature = "toml")]
+    pub async fn record_snapshot_async<C>(&self, connection: &mut C) -> Result<Schema, Error>
+    where
+        C: AsyncMigrate + AsyncQuery<SchemaRows> + Send,
+    {
+        connection
+            .record_schema_snapshot(&self.migration_table_name)
+            .await
+    }
+
+    /// Compares the schema of the supplied database connection with the snapshot recorded after the last migration,
+    /// listing the tables, columns and indexes that were added, removed or changed since.
+    /// Returns Error if no snapshot was recorded, or if it was recorded before the last applied migration
+    #[cfg(feature = "toml")]
+    pub fn detect_drift<C>(&self, connection: &mut C) -> Result<Vec<SchemaChange>, Error>
+    where
+        C: Migrate + Query<SchemaRows>,
+    {
+        let snapshot = connection.get_schema_snapshot(&self.migration_table_name)?;
+        let last_applied = connection.get_last_applied_migration(&self.migration_table_name)?;
+        let current = connection.introspect_schema(&self.migration_table_name)?;
+        detect_drift(snapshot, last_applied, &current)
+    }
+
+    /// Compares the schema with the recorded snapshot asynchronously, see [`Runner::detect_drift`]
+    #[cfg(feature = "toml")]
+    pub async fn detect_drift_async<C>(
+        &self,
+        connection: &mut C,
+    ) -> Result<Vec<SchemaChange>, Error>
+    where
+        C: AsyncMigrate + AsyncQuery<SchemaRows> + Send,
+    {
+        let snapshot = connection
+            .get_schema_snapshot(&self.migration_table_name)
+            .await?;
+        let last_applied = connection
+            .get_last_applied_migration(&self.migration_table_name)
+            .await?;
+        let current = connection
+            .introspect_schema(&self.migration_table_name)
+            .await?;
+        detect_drift(snapshot, last_applied, &current)
+    }
+}
+
+// the version header keeps the file deterministic, it only changes along with the migrations
+fn write_schema_file(
+    location: &Path,
+    schema: &Schema,
+    dialect: ConfigDbType,
+    last_applied: Option<Migration>,
+) -> Result<(), Error> {
+    let mut contents = String::new();
+    if let Some(migration) = last_applied {
+        contents.push_str(&format!(
+            "-- schema at version {}, dumped by refinery\n\n",
+            migration.version_str()
+        ));
+    }
+    contents.push_str(&schema.to_sql(dialect));
+    std::fs::write(location, contents)
+        .map_err(|err| Error::new(Kind::InvalidSchemaFile(location.to_path_buf(), err), None))
 }
 
 pub struct RunIterator<'a, C> {
@@ -0,0 +1,955 @@
+//! Introspection of the tables, columns, primary keys and indexes of a database schema
+use std::fmt;
+
+use crate::config::ConfigDbType;
+#[cfg(feature = "toml")]
+use crate::error::{Error, Kind};
+
+/// Rows returned by the schema introspection queries, every value is read as text
+pub type SchemaRows = Vec<Vec<Option<String>>>;
+
+/// A normalized description of a database schema, tables are ordered by name
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Schema {
+    pub tables: Vec<Table>,
+}
+
+/// A table of a [`Schema`], columns are ordered by position and indexes by name
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Table {
+    pub name: String,
+    pub columns: Vec<Column>,
+    pub primary_key: Vec<String>,
+    pub indexes: Vec<Index>,
+}
+
+/// A column of a [`Table`], `data_type` and `default` are as reported by the database
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Column {
+    pub name: String,
+    pub data_type: String,
+    pub nullable: bool,
+    pub default: Option<String>,
+}
+
+/// An index of a [`Table`], primary keys aren't listed as indexes
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Index {
+    pub name: String,
+    pub columns: Vec<String>,
+    pub unique: bool,
+}
+
+// each dialect has three queries, returning rows of:
+// - table, column, data type, nullable (YES or NO), default
+// - table, primary key column
+// - table, index, unique (1 or 0), index column
+// ordered by table and then column position
+
+const SQLITE_QUERIES: [&str; 3] = [
+    r#"SELECT m.name, p.name, p.type, CASE WHEN p."notnull" = 0 THEN 'YES' ELSE 'NO' END, p.dflt_value
+    FROM sqlite_master m JOIN pragma_table_info(m.name) p
+    WHERE m.type = 'table' AND m.name NOT LIKE 'sqlite_%'
+    ORDER BY m.name, p.cid"#,
+    r#"SELECT m.name, p.name
+    FROM sqlite_master m JOIN pragma_table_info(m.name) p
+    WHERE m.type = 'table' AND m.name NOT LIKE 'sqlite_%' AND p.pk > 0
+    ORDER BY m.name, p.pk"#,
+    // indexes backing unique constraints are named sqlite_autoindex_*, a reserved prefix for created indexes
+    r#"SELECT m.name, CASE WHEN il.origin = 'u' THEN substr(il.name, 8) ELSE il.name END, CAST(il."unique" AS TEXT), ii.name
+    FROM sqlite_master m JOIN pragma_index_list(m.name) il JOIN pragma_index_info(il.name) ii
+    WHERE m.type = 'table' AND m.name NOT LIKE 'sqlite_%' AND il.origin <> 'pk' AND ii.name IS NOT NULL
+    ORDER BY m.name, il.name, ii.seqno"#,
+];
+
+const POSTGRES_QUERIES: [&str; 3] = [
+    "SELECT c.relname::text, a.attname::text, format_type(a.atttypid, a.atttypmod),
+        CASE WHEN a.attnotnull THEN 'NO' ELSE 'YES' END, pg_get_expr(d.adbin, d.adrelid)
+    FROM pg_class c
+    JOIN pg_namespace n ON n.oid = c.relnamespace
+    JOIN pg_attribute a ON a.attrelid = c.oid
+    LEFT JOIN pg_attrdef d ON d.adrelid = c.oid AND d.adnum = a.attnum
+    WHERE n.nspname = current_schema() AND c.relkind IN ('r', 'p') AND a.attnum > 0 AND NOT a.attisdropped
+    ORDER BY c.relname, a.attnum",
+    "SELECT c.relname::text, a.attname::text
+    FROM pg_index ix
+    JOIN pg_class c ON c.oid = ix.indrelid
+    JOIN pg_namespace n ON n.oid = c.relnamespace
+    JOIN LATERAL unnest(ix.indkey::int2[]) WITH ORDINALITY AS k(attnum, ord) ON true
+    JOIN pg_attribute a ON a.attrelid = c.oid AND a.attnum = k.attnum
+    WHERE n.nspname = current_schema() AND ix.indisprimary
+    ORDER BY c.relname, k.ord",
+    "SELECT c.relname::text, i.relname::text, CASE WHEN ix.indisunique THEN '1' ELSE '0' END, a.attname::text
+    FROM pg_index ix
+    JOIN pg_class c ON c.oid = ix.indrelid
+    JOIN pg_class i ON i.oid = ix.indexrelid
+    JOIN pg_namespace n ON n.oid = c.relnamespace
+    JOIN LATERAL unnest(ix.indkey::int2[]) WITH ORDINALITY AS k(attnum, ord) ON true
+    JOIN pg_attribute a ON a.attrelid = c.oid AND a.attnum = k.attnum
+    WHERE n.nspname = current_schema() AND NOT ix.indisprimary
+    ORDER BY c.relname, i.relname, k.ord",
+];
+
+const MYSQL_QUERIES: [&str; 3] = [
+    // literal defaults are reported unquoted
+    "SELECT c.TABLE_NAME, c.COLUMN_NAME,
+        CONCAT(c.COLUMN_TYPE, CASE WHEN c.EXTRA LIKE '%auto_increment%' THEN ' AUTO_INCREMENT' ELSE '' END),
+        c.IS_NULLABLE,
+        CASE WHEN c.COLUMN_DEFAULT IS NULL THEN NULL
+            WHEN c.EXTRA LIKE '%DEFAULT_GENERATED%' THEN c.COLUMN_DEFAULT
+            ELSE QUOTE(c.COLUMN_DEFAULT) END
+    FROM information_schema.COLUMNS c
+    JOIN information_schema.TABLES t ON t.TABLE_SCHEMA = c.TABLE_SCHEMA AND t.TABLE_NAME = c.TABLE_NAME
+    WHERE c.TABLE_SCHEMA = DATABASE() AND t.TABLE_TYPE = 'BASE TABLE'
+    ORDER BY c.TABLE_NAME, c.ORDINAL_POSITION",
+    "SELECT TABLE_NAME, COLUMN_NAME
+    FROM information_schema.KEY_COLUMN_USAGE
+    WHERE TABLE_SCHEMA = DATABASE() AND CONSTRAINT_NAME = 'PRIMARY'
+    ORDER BY TABLE_NAME, ORDINAL_POSITION",
+    "SELECT TABLE_NAME, INDEX_NAME, CASE WHEN NON_UNIQUE = 0 THEN '1' ELSE '0' END, COLUMN_NAME
+    FROM information_schema.STATISTICS
+    WHERE TABLE_SCHEMA = DATABASE() AND INDEX_NAME <> 'PRIMARY' AND COLUMN_NAME IS NOT NULL
+    ORDER BY TABLE_NAME, INDEX_NAME, SEQ_IN_INDEX",
+];
+
+const MSSQL_QUERIES: [&str; 3] = [
+    "SELECT c.TABLE_NAME, c.COLUMN_NAME,
+        c.DATA_TYPE
+            + CASE WHEN c.CHARACTER_MAXIMUM_LENGTH = -1 THEN '(max)'
+                WHEN c.CHARACTER_MAXIMUM_LENGTH IS NOT NULL THEN '(' + CAST(c.CHARACTER_MAXIMUM_LENGTH AS VARCHAR(10)) + ')'
+                WHEN c.DATA_TYPE IN ('decimal', 'numeric')
+                    THEN '(' + CAST(c.NUMERIC_PRECISION AS VARCHAR(10)) + ',' + CAST(c.NUMERIC_SCALE AS VARCHAR(10)) + ')'
+                WHEN c.DATA_TYPE IN ('datetime2', 'time', 'datetimeoffset')
+                    THEN '(' + CAST(c.DATETIME_PRECISION AS VARCHAR(10)) + ')'
+                ELSE '' END
+            + CASE WHEN COLUMNPROPERTY(OBJECT_ID(c.TABLE_SCHEMA + '.' + c.TABLE_NAME), c.COLUMN_NAME, 'IsIdentity') = 1
+                THEN ' IDENTITY(1,1)' ELSE '' END,
+        c.IS_NULLABLE, c.COLUMN_DEFAULT
+    FROM INFORMATION_SCHEMA.COLUMNS c
+    JOIN INFORMATION_SCHEMA.TABLES t ON t.TABLE_SCHEMA = c.TABLE_SCHEMA AND t.TABLE_NAME = c.TABLE_NAME
+    WHERE c.TABLE_SCHEMA = SCHEMA_NAME() AND t.TABLE_TYPE = 'BASE TABLE'
+    ORDER BY c.TABLE_NAME, c.ORDINAL_POSITION",
+    "SELECT tc.TABLE_NAME, kcu.COLUMN_NAME
+    FROM INFORMATION_SCHEMA.TABLE_CONSTRAINTS tc
+    JOIN INFORMATION_SCHEMA.KEY_COLUMN_USAGE kcu
+        ON kcu.CONSTRAINT_SCHEMA = tc.CONSTRAINT_SCHEMA AND kcu.CONSTRAINT_NAME = tc.CONSTRAINT_NAME
+    WHERE tc.CONSTRAINT_TYPE = 'PRIMARY KEY' AND tc.TABLE_SCHEMA = SCHEMA_NAME()
+    ORDER BY tc.TABLE_NAME, kcu.ORDINAL_POSITION",
+    "SELECT t.name, i.name, CASE WHEN i.is_unique = 1 THEN '1' ELSE '0' END, c.name
+    FROM sys.indexes i
+    JOIN sys.tables t ON t.object_id = i.object_id
+    JOIN sys.index_columns ic ON ic.object_id = i.object_id AND ic.index_id = i.index_id
+    JOIN sys.columns c ON c.object_id = ic.object_id AND c.column_id = ic.column_id
+    WHERE SCHEMA_NAME(t.schema_id) = SCHEMA_NAME() AND i.is_primary_key = 0 AND i.name IS NOT NULL
+        AND ic.is_included_column = 0
+    ORDER BY t.name, i.name, ic.key_ordinal",
+];
+
+// Get the queries used to introspect the schema of a database of the given dialect
+pub(crate) fn introspection_queries(dialect: ConfigDbType) -> [&'static str; 3] {
+    match dialect {
+        ConfigDbType::Sqlite => SQLITE_QUERIES,
+        ConfigDbType::Postgres => POSTGRES_QUERIES,
+        ConfigDbType::Mysql => MYSQL_QUERIES,
+        ConfigDbType::Mssql => MSSQL_QUERIES,
+    }
+}
+
+// queries listing the objects a `Schema` can't describe, so a baseline would leave them out,
+// each returning rows of kind and name
+
+const SQLITE_UNCAPTURED_QUERIES: [&str; 5] = [
+    "SELECT type, name FROM sqlite_master WHERE type IN ('view', 'trigger') ORDER BY name",
+    r#"SELECT 'foreign key', m.name || '.' || f."from"
+    FROM sqlite_master m JOIN pragma_foreign_key_list(m.name) f
+    WHERE m.type = 'table'
+    ORDER BY m.name, f.id, f.seq"#,
+    // constraints aren't listed by any pragma, so look for them in the statement that created the table
+    "SELECT 'check constraint', name FROM sqlite_master
+    WHERE type = 'table' AND (upper(sql) GLOB '*[^A-Z0-9_]CHECK(*' OR upper(sql) GLOB '*[^A-Z0-9_]CHECK (*')
+    ORDER BY name",
+    "SELECT 'generated column', m.name || '.' || x.name
+    FROM sqlite_master m JOIN pragma_table_xinfo(m.name) x
+    WHERE m.type = 'table' AND x.hidden IN (2, 3)
+    ORDER BY m.name, x.cid",
+    // columns of indexes on expressions have the cid -2
+    "SELECT DISTINCT 'index', il.name
+    FROM sqlite_master m JOIN pragma_index_list(m.name) il JOIN pragma_index_xinfo(il.name) ii
+    WHERE m.type = 'table' AND m.name NOT LIKE 'sqlite_%' AND (il.partial = 1 OR (ii.key = 1 AND ii.cid = -2))
+    ORDER BY il.name",
+];
+
+const POSTGRES_UNCAPTURED_QUERIES: [&str; 7] = [
+    "SELECT CASE con.contype WHEN 'f' THEN 'foreign key' WHEN 'c' THEN 'check constraint' ELSE 'exclusion constraint' END,
+        con.conname::text
+    FROM pg_constraint con
+    JOIN pg_namespace n ON n.oid = con.connamespace
+    WHERE n.nspname = current_schema() AND con.contype IN ('f', 'c', 'x')
+    ORDER BY con.conname",
+    // sequences of serial and identity columns depend on their column
+    "SELECT CASE WHEN c.relispartition THEN 'partition' WHEN c.relkind = 'v' THEN 'view'
+            WHEN c.relkind = 'm' THEN 'materialized view' WHEN c.relkind = 'f' THEN 'foreign table'
+            WHEN c.relkind = 'p' THEN 'partitioned table' WHEN c.relkind = 'c' THEN 'type' ELSE 'sequence' END,
+        c.relname::text
+    FROM pg_class c
+    JOIN pg_namespace n ON n.oid = c.relnamespace
+    WHERE n.nspname = current_schema() AND (c.relkind IN ('v', 'm', 'f', 'p', 'c') OR c.relispartition
+        OR (c.relkind = 'S' AND NOT EXISTS (SELECT 1 FROM pg_depend d
+            WHERE d.classid = 'pg_class'::regclass AND d.objid = c.oid AND d.deptype IN ('a', 'i'))))
+    ORDER BY c.relname",
+    "SELECT CASE WHEN a.attidentity <> '' THEN 'identity column' ELSE 'generated column' END,
+        c.relname || '.' || a.attname
+    FROM pg_attribute a
+    JOIN pg_class c ON c.oid = a.attrelid
+    JOIN pg_namespace n ON n.oid = c.relnamespace
+    WHERE n.nspname = current_schema() AND c.relkind IN ('r', 'p') AND a.attnum > 0 AND NOT a.attisdropped
+        AND (a.attidentity <> '' OR a.attgenerated <> '')
+    ORDER BY c.relname, a.attnum",
+    "SELECT 'index', i.relname::text
+    FROM pg_index ix
+    JOIN pg_class i ON i.oid = ix.indexrelid
+    JOIN pg_namespace n ON n.oid = i.relnamespace
+    WHERE n.nspname = current_schema() AND (ix.indexprs IS NOT NULL OR ix.indpred IS NOT NULL)
+    ORDER BY i.relname",
+    "SELECT 'trigger', t.tgname::text
+    FROM pg_trigger t
+    JOIN pg_class c ON c.oid = t.tgrelid
+    JOIN pg_namespace n ON n.oid = c.relnamespace
+    WHERE n.nspname = current_schema() AND NOT t.tgisinternal
+    ORDER BY t.tgname",
+    "SELECT 'function', p.proname::text
+    FROM pg_proc p
+    JOIN pg_namespace n ON n.oid = p.pronamespace
+    WHERE n.nspname = current_schema()
+    ORDER BY p.proname",
+    // enums, domains and ranges, composite types are listed as relations
+    "SELECT 'type', t.typname::text
+    FROM pg_type t
+    JOIN pg_namespace n ON n.oid = t.typnamespace
+    WHERE n.nspname = current_schema() AND t.typtype IN ('e', 'd', 'r')
+    ORDER BY t.typname",
+];
+
+const MYSQL_UNCAPTURED_QUERIES: [&str; 7] = [
+    "SELECT CASE WHEN CONSTRAINT_TYPE = 'FOREIGN KEY' THEN 'foreign key' ELSE 'check constraint' END, CONSTRAINT_NAME
+    FROM information_schema.TABLE_CONSTRAINTS
+    WHERE TABLE_SCHEMA = DATABASE() AND CONSTRAINT_TYPE IN ('FOREIGN KEY', 'CHECK')
+    ORDER BY CONSTRAINT_NAME",
+    "SELECT 'view', TABLE_NAME FROM information_schema.VIEWS WHERE TABLE_SCHEMA = DATABASE() ORDER BY TABLE_NAME",
+    "SELECT 'trigger', TRIGGER_NAME
+    FROM information_schema.TRIGGERS
+    WHERE TRIGGER_SCHEMA = DATABASE()
+    ORDER BY TRIGGER_NAME",
+    "SELECT LOWER(ROUTINE_TYPE), ROUTINE_NAME
+    FROM information_schema.ROUTINES
+    WHERE ROUTINE_SCHEMA = DATABASE()
+    ORDER BY ROUTINE_NAME",
+    "SELECT 'event', EVENT_NAME FROM information_schema.EVENTS WHERE EVENT_SCHEMA = DATABASE() ORDER BY EVENT_NAME",
+    "SELECT 'generated column', CONCAT(TABLE_NAME, '.', COLUMN_NAME)
+    FROM information_schema.COLUMNS
+    WHERE TABLE_SCHEMA = DATABASE() AND COALESCE(GENERATION_EXPRESSION, '') <> ''
+    ORDER BY TABLE_NAME, ORDINAL_POSITION",
+    // indexes on expressions have no column, those on column prefixes a sub part
+    "SELECT DISTINCT 'index', INDEX_NAME
+    FROM information_schema.STATISTICS
+    WHERE TABLE_SCHEMA = DATABASE() AND (COLUMN_NAME IS NULL OR SUB_PART IS NOT NULL)
+    ORDER BY INDEX_NAME",
+];
+
+const MSSQL_UNCAPTURED_QUERIES: [&str; 7] = [
+    "SELECT 'foreign key', name FROM sys.foreign_keys WHERE SCHEMA_NAME(schema_id) = SCHEMA_NAME() ORDER BY name",
+    "SELECT 'check constraint', name
+    FROM sys.check_constraints
+    WHERE SCHEMA_NAME(schema_id) = SCHEMA_NAME()
+    ORDER BY name",
+    "SELECT 'view', name FROM sys.views WHERE SCHEMA_NAME(schema_id) = SCHEMA_NAME() ORDER BY name",
+    "SELECT 'trigger', tr.name
+    FROM sys.triggers tr
+    JOIN sys.tables t ON t.object_id = tr.parent_id
+    WHERE SCHEMA_NAME(t.schema_id) = SCHEMA_NAME()
+    ORDER BY tr.name",
+    // procedures, functions, synonyms and sequences
+    "SELECT LOWER(REPLACE(type_desc, '_', ' ')), name
+    FROM sys.objects
+    WHERE SCHEMA_NAME(schema_id) = SCHEMA_NAME() AND type IN ('P', 'FN', 'IF', 'TF', 'SN', 'SO')
+    ORDER BY name",
+    "SELECT 'computed column', t.name + '.' + c.name
+    FROM sys.computed_columns c
+    JOIN sys.tables t ON t.object_id = c.object_id
+    WHERE SCHEMA_NAME(t.schema_id) = SCHEMA_NAME()
+    ORDER BY t.name, c.column_id",
+    "SELECT 'index', i.name
+    FROM sys.indexes i
+    JOIN sys.tables t ON t.object_id = i.object_id
+    WHERE SCHEMA_NAME(t.schema_id) = SCHEMA_NAME() AND (i.has_filter = 1 OR EXISTS (SELECT 1 FROM sys.index_columns ic
+        WHERE ic.object_id = i.object_id AND ic.index_id = i.index_id AND ic.is_included_column = 1))
+    ORDER BY i.name",
+];
+
+// Get the queries listing the objects of a database of the given dialect that a baseline can't hold
+pub(crate) fn uncaptured_objects_queries(dialect: ConfigDbType) -> &'static [&'static str] {
+    match dialect {
+        ConfigDbType::Sqlite => &SQLITE_UNCAPTURED_QUERIES,
+        ConfigDbType::Postgres => &POSTGRES_UNCAPTURED_QUERIES,
+        ConfigDbType::Mysql => &MYSQL_UNCAPTURED_QUERIES,
+        ConfigDbType::Mssql => &MSSQL_UNCAPTURED_QUERIES,
+    }
+}
+
+// Describe an object listed by an uncaptured objects query, i.e. `view active_persons`
+pub(crate) fn uncaptured_object(row: &[Option<String>]) -> String {
+    format!(
+        "{} {}",
+        value(row, 0).unwrap_or_default(),
+        value(row, 1).unwrap_or_default()
+    )
+}
+
+// Get the name of the table the schema snapshot is recorded in, next to the migrations table
+pub(crate) fn snapshot_table_name(migration_table_name: &str) -> String {
+    format!("{}_snapshot", migration_table_name)
+}
+
+#[cfg(feature = "toml")]
+pub(crate) fn assert_snapshot_table_query(
+    dialect: ConfigDbType,
+    migration_table_name: &str,
+) -> String {
+    let table_name = snapshot_table_name(migration_table_name);
+    match dialect {
+        ConfigDbType::Mssql => format!(
+            "IF NOT EXISTS(SELECT 1 FROM sys.Tables WHERE Name = N'{table_name}')
+         BEGIN
+           CREATE TABLE {table_name}(version VARCHAR(255), snapshot NVARCHAR(MAX));
+         END"
+        ),
+        ConfigDbType::Mysql => format!(
+            "CREATE TABLE IF NOT EXISTS {table_name}(version VARCHAR(255), snapshot LONGTEXT);"
+        ),
+        ConfigDbType::Postgres | ConfigDbType::Sqlite => {
+            format!("CREATE TABLE IF NOT EXISTS {table_name}(version VARCHAR(255), snapshot TEXT);")
+        }
+    }
+}
+
+#[cfg(feature = "toml")]
+// Replaces the recorded snapshot, `version` being the version of the last applied migration
+pub(crate) fn record_snapshot_queries(
+    dialect: ConfigDbType,
+    migration_table_name: &str,
+    version: Option<&str>,
+    snapshot: &str,
+) -> [String; 3] {
+    let table_name = snapshot_table_name(migration_table_name);
+    let mut snapshot = snapshot.replace('\'', "''");
+    if dialect == ConfigDbType::Mysql {
+        // backslashes start escape sequences in mysql string literals
+        snapshot = snapshot.replace('\\', "\\\\");
+    }
+    [
+        assert_snapshot_table_query(dialect, migration_table_name),
+        format!("DELETE FROM {table_name};"),
+        format!(
+            "INSERT INTO {table_name} (version, snapshot) VALUES ({}, {}'{snapshot}');",
+            version.map_or("NULL".to_string(), |version| format!("'{version}'")),
+            if dialect == ConfigDbType::Mssql {
+                "N"
+            } else {
+                ""
+            },
+        ),
+    ]
+}
+
+// Get the query listing the snapshot table, which has no rows when no snapshot was ever recorded
+#[cfg(feature = "toml")]
+pub(crate) fn snapshot_table_exists_query(
+    dialect: ConfigDbType,
+    migration_table_name: &str,
+) -> String {
+    let table_name = snapshot_table_name(migration_table_name);
+    match dialect {
+        ConfigDbType::Mssql => format!("SELECT name FROM sys.tables WHERE name = N'{table_name}';"),
+        ConfigDbType::Mysql => format!(
+            "SELECT TABLE_NAME FROM information_schema.TABLES
+            WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME = '{table_name}';"
+        ),
+        // unquoted table names are folded to lower case
+        ConfigDbType::Postgres => format!(
+            "SELECT c.relname::text FROM pg_class c
+            JOIN pg_namespace n ON n.oid = c.relnamespace
+            WHERE n.nspname = current_schema() AND c.relname = lower('{table_name}');"
+        ),
+        ConfigDbType::Sqlite => format!(
+            "SELECT name FROM sqlite_master WHERE type = 'table' AND name = '{table_name}';"
+        ),
+    }
+}
+
+#[cfg(feature = "toml")]
+pub(crate) fn get_snapshot_query(migration_table_name: &str) -> String {
+    format!(
+        "SELECT version, snapshot FROM {};",
+        snapshot_table_name(migration_table_name)
+    )
+}
+
+fn value(row: &[Option<String>], index: usize) -> Option<String> {
+    row.get(index).cloned().flatten()
+}
+
+fn table_mut(tables: &mut Vec<Table>, name: String) -> &mut Table {
+    match tables.iter().position(|table| table.name == name) {
+        Some(position) => &mut tables[position],
+        None => {
+            tables.push(Table {
+                name,
+                ..Default::default()
+            });
+            // safe to call unwrap as we just pushed a table
+            tables.last_mut().unwrap()
+        }
+    }
+}
+
+impl Schema {
+    // Build the schema from the rows of the introspection queries, leaving out refinery's own tables
+    pub(crate) fn from_rows(
+        columns: SchemaRows,
+        primary_keys: SchemaRows,
+        indexes: SchemaRows,
+        migration_table_name: &str,
+    ) -> Schema {
+        let mut tables = Vec::new();
+
+        for row in columns {
+            let table = table_mut(&mut tables, value(&row, 0).unwrap_or_default());
+            table.columns.push(Column {
+                name: value(&row, 1).unwrap_or_default(),
+                data_type: value(&row, 2).unwrap_or_default(),
+                nullable: value(&row, 3).as_deref() == Some("YES"),
+                default: value(&row, 4),
+            });
+        }
+
+        for row in primary_keys {
+            let table = table_mut(&mut tables, value(&row, 0).unwrap_or_default());
+            table.primary_key.push(value(&row, 1).unwrap_or_default());
+        }
+
+        for row in indexes {
+            let table = table_mut(&mut tables, value(&row, 0).unwrap_or_default());
+            let name = value(&row, 1).unwrap_or_default();
+            let column = value(&row, 3).unwrap_or_default();
+            match table.indexes.iter_mut().find(|index| index.name == name) {
+                Some(index) => index.columns.push(column),
+                None => table.indexes.push(Index {
+                    name,
+                    columns: vec![column],
+                    unique: value(&row, 2).as_deref() == Some("1"),
+                }),
+            }
+        }
+
+        let snapshot_table_name = snapshot_table_name(migration_table_name);
+        tables.retain(|table| {
+            !table.name.eq_ignore_ascii_case(migration_table_name)
+                && !table.name.eq_ignore_ascii_case(&snapshot_table_name)
+        });
+        // the ordering of the queries depends on the database collation
+        tables.sort_by(|a, b| a.name.cmp(&b.name));
+        for table in tables.iter_mut() {
+            table.indexes.sort_by(|a, b| a.name.cmp(&b.name));
+        }
+
+        Schema { tables }
+    }
+
+    /// Get a table by name
+    pub fn table(&self, name: &str) -> Option<&Table> {
+        self.tables.iter().find(|table| table.name == name)
+    }
+
+    /// Compare the schema with the `current` one, listing the changes that lead from one to the other
+    /// ordered by table name, and for each table by columns, primary key and then indexes
+    pub fn diff(&self, current: &Schema) -> Vec<SchemaChange> {
+        let mut names = self
+            .tables
+            .iter()
+            .chain(current.tables.iter())
+            .map(|table| table.name.as_str())
+            .collect::<Vec<_>>();
+        names.sort();
+        names.dedup();
+
+        let mut changes = Vec::new();
+        for name in names {
+            match (self.table(name), current.table(name)) {
+                (Some(before), Some(after)) => before.diff(after, &mut changes),
+                (Some(before), None) => changes.push(SchemaChange::RemovedTable(before.clone())),
+                (None, Some(after)) => changes.push(SchemaChange::AddedTable(after.clone())),
+                (None, None) => unreachable!(),
+            }
+        }
+        changes
+    }
+
+    #[cfg(feature = "toml")]
+    pub(crate) fn to_snapshot(&self) -> Result<String, Error> {
+        toml::to_string(self)
+            .map_err(|err| Error::new(Kind::InvalidSnapshot(err.to_string()), None))
+    }
+
+    #[cfg(feature = "toml")]
+    pub(crate) fn from_snapshot(snapshot: &str) -> Result<Schema, Error> {
+        toml::from_str(snapshot)
+            .map_err(|err| Error::new(Kind::InvalidSnapshot(err.to_string()), None))
+    }
+
+    /// Render the schema as the statements that create it on a database of the given dialect.
+    /// The output is deterministic, tables are followed by their indexes and ordered by name
+    pub fn to_sql(&self, dialect: ConfigDbType) -> String {
+        self.tables
+            .iter()
+            .map(|table| table.to_sql(dialect))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+impl Table {
+    /// Get a column by name
+    pub fn column(&self, name: &str) -> Option<&Column> {
+        self.columns.iter().find(|column| column.name == name)
+    }
+
+    /// Get an index by name
+    pub fn index(&self, name: &str) -> Option<&Index> {
+        self.indexes.iter().find(|index| index.name == name)
+    }
+
+    fn diff(&self, current: &Table, changes: &mut Vec<SchemaChange>) {
+        for before in &self.columns {
+            match current.column(&before.name) {
+                Some(after) if after != before => changes.push(SchemaChange::ChangedColumn {
+                    table: self.name.clone(),
+                    before: before.clone(),
+                    after: after.clone(),
+                }),
+                Some(_) => {}
+                None => changes.push(SchemaChange::RemovedColumn {
+                    table: self.name.clone(),
+                    column: before.clone(),
+                }),
+            }
+        }
+        for after in &current.columns {
+            if self.column(&after.name).is_none() {
+                changes.push(SchemaChange::AddedColumn {
+                    table: self.name.clone(),
+                    column: after.clone(),
+                });
+            }
+        }
+
+        if self.primary_key != current.primary_key {
+            changes.push(SchemaChange::ChangedPrimaryKey {
+                table: self.name.clone(),
+                before: self.primary_key.clone(),
+                after: current.primary_key.clone(),
+            });
+        }
+
+        for before in &self.indexes {
+            match current.index(&before.name) {
+                Some(after) if after != before => changes.push(SchemaChange::ChangedIndex {
+                    table: self.name.clone(),
+                    before: before.clone(),
+                    after: after.clone(),
+                }),
+                Some(_) => {}
+                None => changes.push(SchemaChange::RemovedIndex {
+                    table: self.name.clone(),
+                    index: before.clone(),
+                }),
+            }
+        }
+        for after in &current.indexes {
+            if self.index(&after.name).is_none() {
+                changes.push(SchemaChange::AddedIndex {
+                    table: self.name.clone(),
+                    index: after.clone(),
+                });
+            }
+        }
+    }
+
+    fn to_sql(&self, dialect: ConfigDbType) -> String {
+        let mut definitions = self
+            .columns
+            .iter()
+            .map(|column| column.to_sql(dialect))
+            .collect::<Vec<_>>();
+        if !self.primary_key.is_empty() {
+            definitions.push(format!(
+                "PRIMARY KEY ({})",
+                quote_all(&self.primary_key, dialect)
+            ));
+        }
+
+        let mut sql = format!(
+            "CREATE TABLE {} (\n    {}\n);\n",
+            quote(&self.name, dialect),
+            definitions.join(",\n    ")
+        );
+        for index in &self.indexes {
+            sql.push_str(&format!(
+                "CREATE {}INDEX {} ON {} ({});\n",
+                if index.unique { "UNIQUE " } else { "" },
+                quote(&index.name, dialect),
+                quote(&self.name, dialect),
+                quote_all(&index.columns, dialect)
+            ));
+        }
+        sql
+    }
+}
+
+impl Column {
+    fn to_sql(&self, dialect: ConfigDbType) -> String {
+        let mut data_type = self.data_type.as_str();
+        let mut default = self.default.as_deref();
+
+        // columns backed by a sequence, which doesn't exist until the column is created
+        if dialect == ConfigDbType::Postgres
+            && default.is_some_and(|default| default.starts_with("nextval("))
+        {
+            let serial = match data_type {
+                "smallint" => Some("smallserial"),
+                "integer" => Some("serial"),
+                "bigint" => Some("bigserial"),
+                _ => None,
+            };
+            if let Some(serial) = serial {
+                data_type = serial;
+                default = None;
+            }
+        }
+
+        let mut sql = format!("{} {}", quote(&self.name, dialect), data_type);
+        if !self.nullable {
+            sql.push_str(" NOT NULL");
+        }
+        if let Some(default) = default {
+            sql.push_str(" DEFAULT ");
+            sql.push_str(default);
+        }
+        sql
+    }
+}
+
+/// A difference between two [`Schema`]s, as listed by [`Schema::diff`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(tag = "change", rename_all = "snake_case")
+)]
+pub enum SchemaChange {
+    AddedTable(Table),
+    RemovedTable(Table),
+    AddedColumn {
+        table: String,
+        column: Column,
+    },
+    RemovedColumn {
+        table: String,
+        column: Column,
+    },
+    ChangedColumn {
+        table: String,
+        before: Column,
+        after: Column,
+    },
+    ChangedPrimaryKey {
+        table: String,
+        before: Vec<String>,
+        after: Vec<String>,
+    },
+    AddedIndex {
+        table: String,
+        index: Index,
+    },
+    RemovedIndex {
+        table: String,
+        index: Index,
+    },
+    ChangedIndex {
+        table: String,
+        before: Index,
+        after: Index,
+    },
+}
+
+impl fmt::Display for Column {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "{}", self.data_type)?;
+        if !self.nullable {
+            write!(fmt, " NOT NULL")?;
+        }
+        if let Some(default) = &self.default {
+            write!(fmt, " DEFAULT {}", default)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Index {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        if self.unique {
+            write!(fmt, "UNIQUE ")?;
+        }
+        write!(fmt, "({})", self.columns.join(", "))
+    }
+}
+
+impl fmt::Display for SchemaChange {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SchemaChange::AddedTable(table) => write!(fmt, "added table {}", table.name),
+            SchemaChange::RemovedTable(table) => write!(fmt, "removed table {}", table.name),
+            SchemaChange::AddedColumn { table, column } => {
+                write!(fmt, "added column {}.{} {}", table, column.name, column)
+            }
+            SchemaChange::RemovedColumn { table, column } => {
+                write!(fmt, "removed column {}.{} {}", table, column.name, column)
+            }
+            SchemaChange::ChangedColumn {
+                table,
+                before,
+                after,
+            } => write!(
+                fmt,
+                "changed column {}.{} from {} to {}",
+                table, before.name, before, after
+            ),
+            SchemaChange::ChangedPrimaryKey {
+                table,
+                before,
+                after,
+            } => write!(
+                fmt,
+                "changed primary key of {} from ({}) to ({})",
+                table,
+                before.join(", "),
+                after.join(", ")
+            ),
+            SchemaChange::AddedIndex { table, index } => {
+                write!(fmt, "added index {} on {} {}", index.name, table, index)
+            }
+            SchemaChange::RemovedIndex { table, index } => {
+                write!(fmt, "removed index {} on {} {}", index.name, table, index)
+            }
+            SchemaChange::ChangedIndex {
+                table,
+                before,
+                after,
+            } => write!(
+                fmt,
+                "changed index {} on {} from {} to {}",
+                before.name, table, before, after
+            ),
+        }
+    }
+}
+
+fn quote(identifier: &str, dialect: ConfigDbType) -> String {
+    match dialect {
+        ConfigDbType::Mysql => format!("`{}`", identifier.replace('`', "``")),
+        ConfigDbType::Mssql => format!("[{}]", identifier.replace(']', "]]")),
+        ConfigDbType::Postgres | ConfigDbType::Sqlite => {
+            format!("\"{}\"", identifier.replace('"', "\"\""))
+        }
+    }
+}
+
+fn quote_all(identifiers: &[String], dialect: ConfigDbType) -> String {
+    identifiers
+        .iter()
+        .map(|identifier| quote(identifier, dialect))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Column, Index, Schema, SchemaChange, SchemaRows};
+    use crate::config::ConfigDbType;
+
+    fn rows(rows: &[&[Option<&str>]]) -> SchemaRows {
+        rows.iter()
+            .map(|row| row.iter().map(|value| value.map(String::from)).collect())
+            .collect()
+    }
+
+    fn get_schema() -> Schema {
+        Schema::from_rows(
+            rows(&[
+                &[
+                    Some("users"),
+                    Some("id"),
+                    Some("integer"),
+                    Some("NO"),
+                    Some("nextval('users_id_seq'::regclass)"),
+                ],
+                &[
+                    Some("users"),
+                    Some("email"),
+                    Some("text"),
+                    Some("YES"),
+                    None,
+                ],
+                &[
+                    Some("refinery_schema_history"),
+                    Some("version"),
+                    Some("integer"),
+                    Some("NO"),
+                    None,
+                ],
+                &[
+                    Some("cars"),
+                    Some("brand"),
+                    Some("character varying(255)"),
+                    Some("NO"),
+                    Some("'none'::character varying"),
+                ],
+            ]),
+            rows(&[&[Some("users"), Some("id")]]),
+            rows(&[
+                &[
+                    Some("users"),
+                    Some("users_email_key"),
+                    Some("1"),
+                    Some("email"),
+                ],
+                &[
+                    Some("cars"),
+                    Some("cars_brand_idx"),
+                    Some("0"),
+                    Some("brand"),
+                ],
+            ]),
+            "refinery_schema_history",
+        )
+    }
+
+    #[test]
+    fn builds_schema_from_rows() {
+        let schema = get_schema();
+        assert_eq!(2, schema.tables.len());
+        assert_eq!("cars", schema.tables[0].name);
+
+        let users = schema.table("users").unwrap();
+        assert_eq!(vec!["id".to_string()], users.primary_key);
+        assert!(!users.column("id").unwrap().nullable);
+        assert!(users.column("email").unwrap().nullable);
+        assert!(users.index("users_email_key").unwrap().unique);
+        assert!(schema.table("refinery_schema_history").is_none());
+    }
+
+    #[test]
+    fn renders_schema_as_sql() {
+        let expected = concat!(
+            "CREATE TABLE \"cars\" (\n",
+            "    \"brand\" character varying(255) NOT NULL DEFAULT 'none'::character varying\n",
+            ");\n",
+            "CREATE INDEX \"cars_brand_idx\" ON \"cars\" (\"brand\");\n",
+            "\n",
+            "CREATE TABLE \"users\" (\n",
+            "    \"id\" serial NOT NULL,\n",
+            "    \"email\" text,\n",
+            "    PRIMARY KEY (\"id\")\n",
+            ");\n",
+            "CREATE UNIQUE INDEX \"users_email_key\" ON \"users\" (\"email\");\n",
+        );
+        assert_eq!(expected, get_schema().to_sql(ConfigDbType::Postgres));
+    }
+
+    #[test]
+    fn quotes_identifiers_per_dialect() {
+        let sql = get_schema().to_sql(ConfigDbType::Mysql);
+        assert!(sql.starts_with("CREATE TABLE `cars` (\n    `brand`"));
+        // sequences are only a postgres concept
+        assert!(sql.contains("`id` integer NOT NULL DEFAULT nextval"));
+
+        let sql = get_schema().to_sql(ConfigDbType::Mssql);
+        assert!(sql.contains("CREATE UNIQUE INDEX [users_email_key] ON [users] ([email]);"));
+    }
+
+    #[test]
+    fn diffs_schemas() {
+        let before = get_schema();
+        let mut after = get_schema();
+        after.tables.remove(0);
+        let users = &mut after.tables[0];
+        users.columns[1].nullable = false;
+        users.columns.push(Column {
+            name: "age".to_string(),
+            data_type: "integer".to_string(),
+            nullable: true,
+            default: None,
+        });
+        users.indexes[0].unique = false;
+
+        let changes = before.diff(&after);
+        assert_eq!(4, changes.len());
+        assert!(matches!(&changes[0], SchemaChange::RemovedTable(table) if table.name == "cars"));
+        assert_eq!(
+            "changed column users.email from text to text NOT NULL",
+            changes[1].to_string()
+        );
+        assert_eq!("added column users.age integer", changes[2].to_string());
+        assert_eq!(
+            SchemaChange::ChangedIndex {
+                table: "users".to_string(),
+                before: Index {
+                    name: "users_email_key".to_string(),
+                    columns: vec!["email".to_string()],
+                    unique: true,
+                },
+                after: after.tables[0].indexes[0].clone(),
+            },
+            changes[3]
+        );
+        assert!(after.diff(&after).is_empty());
+    }
+
+    #[test]
+    fn leaves_out_snapshot_table() {
+        let schema = Schema::from_rows(
+            rows(&[&[
+                Some("refinery_schema_history_snapshot"),
+                Some("snapshot"),
+                Some("text"),
+                Some("YES"),
+                None,
+            ]]),
+            vec![],
+            vec![],
+            "refinery_schema_history",
+        );
+        assert!(schema.tables.is_empty());
+    }
+
+    #[test]
+    #[cfg(feature = "toml")]
+    fn round_trips_snapshot() {
+        let schema = get_schema();
+        let snapshot = schema.to_snapshot().unwrap();
+        assert_eq!(schema, Schema::from_snapshot(&snapshot).unwrap());
+    }
+}
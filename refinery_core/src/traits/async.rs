@@ -1,6 +1,13 @@
+use crate::config::ConfigDbType;
+use crate::error::Kind;
 use crate::error::WrapMigrationError;
+#[cfg(feature = "toml")]
+use crate::schema::{get_snapshot_query, record_snapshot_queries, snapshot_table_exists_query};
+use crate::schema::{
+    introspection_queries, uncaptured_object, uncaptured_objects_queries, Schema, SchemaRows,
+};
 use crate::traits::{
-    verify_migrations, GET_APPLIED_MIGRATIONS_QUERY,
+    select_dialect_variants, verify_migrations, GET_APPLIED_MIGRATIONS_QUERY,
     GET_LAST_APPLIED_MIGRATION_QUERY,
 };
 use crate::{Error, Migration, Report, Target};
@@ -15,6 +22,15 @@ pub trait AsyncTransaction {
         &mut self,
         queries: T,
     ) -> Result<usize, Self::Error>;
+
+    // Executes the queries without wrapping them in a transaction, for migrations that can't run inside one.
+    // Defaults to `execute` for connections that can only run queries inside a transaction
+    async fn execute_outside_transaction<S: AsRef<str> + Send, T: Iterator<Item = S> + Send>(
+        &mut self,
+        queries: T,
+    ) -> Result<usize, Self::Error> {
+        self.execute(queries).await
+    }
 }
 
 #[async_trait]
@@ -22,13 +38,15 @@ pub trait AsyncQuery<T>: AsyncTransaction {
     async fn query(&mut self, query: &str) -> Result<T, Self::Error>;
 }
 
-async fn migrate_inner<T: AsyncTransaction>(
+async fn migrate_inner<T: AsyncTransaction + Send>(
     transaction: &mut T,
     migrations: Vec<Migration>,
     target: Target,
     migration_table_name: &str,
     batched: bool
 ) -> Result<Report, Error> {
+    let migrations = crate::traits::sync::select_target(migrations, target)?;
+    crate::traits::sync::assert_grouped_transactional(&migrations, target, batched)?;
     let mut iter = crate::traits::sync::migrate_reusable(migrations, target, migration_table_name, batched);
 
     while let Some(migration) = iter.next() {
@@ -43,10 +61,14 @@ async fn migrate_inner<T: AsyncTransaction>(
             },
             MigrateReusableResult::Itemized { sql, current_migration } => {
                 log::log!(migration.log_before_tx.level, "{}: {current_migration}", migration.log_before_tx.msg);
-                transaction
-                    .execute([sql].into_iter())
-                    .await
-                    .migration_err(|| format!("error applying single migration async: {current_migration}"), || migration.applied_migrations.cloned())?;
+                let result = if current_migration.metadata().transactional {
+                    transaction.execute([sql].into_iter()).await
+                } else {
+                    transaction.execute_outside_transaction([sql].into_iter()).await
+                };
+                result
+                    .migration_err(|| format!("error applying single migration async: {current_migration}"), || migration.applied_migrations.cloned())
+                    .map_err(|err| err.locate(sql))?;
             }
             MigrateReusableResult::ItemizedMetaInsert { sql, current_migration } => {
                 log::log!(migration.log_before_tx.level, "{}: {current_migration}", migration.log_before_tx.msg);
@@ -78,16 +100,139 @@ where
         GET_APPLIED_MIGRATIONS_QUERY.replace("%MIGRATION_TABLE_NAME%", migration_table_name)
     }
 
+    // The database this connection talks to, used to choose between dialect specific migration variants
+    fn dialect(&self) -> Option<ConfigDbType> {
+        None
+    }
+
+    // Introspects the schema the migrations created, only available for connections that can read schema rows
+    async fn introspect_schema(&mut self, migration_table_name: &str) -> Result<Schema, Error>
+    where
+        Self: AsyncQuery<SchemaRows>,
+    {
+        let dialect = self
+            .dialect()
+            .ok_or_else(|| Error::new(Kind::UnknownDialect, None))?;
+
+        let [columns, primary_keys, indexes] = introspection_queries(dialect);
+        let columns = <Self as AsyncQuery<SchemaRows>>::query(self, columns)
+            .await
+            .migration_err(|| "error introspecting schema", || [].into_iter())?;
+        let primary_keys = <Self as AsyncQuery<SchemaRows>>::query(self, primary_keys)
+            .await
+            .migration_err(|| "error introspecting schema", || [].into_iter())?;
+        let indexes = <Self as AsyncQuery<SchemaRows>>::query(self, indexes)
+            .await
+            .migration_err(|| "error introspecting schema", || [].into_iter())?;
+
+        Ok(Schema::from_rows(
+            columns,
+            primary_keys,
+            indexes,
+            migration_table_name,
+        ))
+    }
+
+    // Lists the objects of the schema a baseline can't hold, i.e. views, triggers or foreign keys
+    async fn uncaptured_schema_objects(&mut self) -> Result<Vec<String>, Error>
+    where
+        Self: AsyncQuery<SchemaRows>,
+    {
+        let dialect = self
+            .dialect()
+            .ok_or_else(|| Error::new(Kind::UnknownDialect, None))?;
+
+        let mut objects = Vec::new();
+        for query in uncaptured_objects_queries(dialect) {
+            let rows = <Self as AsyncQuery<SchemaRows>>::query(self, query)
+                .await
+                .migration_err(|| "error introspecting schema", || [].into_iter())?;
+            objects.extend(rows.iter().map(|row| uncaptured_object(row)));
+        }
+        Ok(objects)
+    }
+
+    // Records a snapshot of the schema along with the version of the last applied migration, to detect drift later on
+    #[cfg(feature = "toml")]
+    async fn record_schema_snapshot(&mut self, migration_table_name: &str) -> Result<Schema, Error>
+    where
+        Self: AsyncQuery<SchemaRows>,
+    {
+        let dialect = self
+            .dialect()
+            .ok_or_else(|| Error::new(Kind::UnknownDialect, None))?;
+
+        self.execute([Self::assert_migrations_table_query(migration_table_name)].into_iter())
+            .await
+            .migration_err(|| "error asserting migrations table", || [].into_iter())?;
+        let version = self
+            .get_last_applied_migration(migration_table_name)
+            .await?
+            .map(|migration| migration.version_str());
+        let schema = self.introspect_schema(migration_table_name).await?;
+        let queries = record_snapshot_queries(
+            dialect,
+            migration_table_name,
+            version.as_deref(),
+            &schema.to_snapshot()?,
+        );
+        self.execute(queries.into_iter())
+            .await
+            .migration_err(|| "error recording schema snapshot", || [].into_iter())?;
+
+        Ok(schema)
+    }
+
+    // Get the recorded schema snapshot along with the version of the last migration applied when it was recorded
+    #[cfg(feature = "toml")]
+    async fn get_schema_snapshot(
+        &mut self,
+        migration_table_name: &str,
+    ) -> Result<Option<(Option<String>, Schema)>, Error>
+    where
+        Self: AsyncQuery<SchemaRows>,
+    {
+        let dialect = self
+            .dialect()
+            .ok_or_else(|| Error::new(Kind::UnknownDialect, None))?;
+
+        // the snapshot table is only created when recording a snapshot
+        let tables = <Self as AsyncQuery<SchemaRows>>::query(
+            self,
+            &snapshot_table_exists_query(dialect, migration_table_name),
+        )
+        .await
+        .migration_err(|| "error getting schema snapshot", || [].into_iter())?;
+        if tables.is_empty() {
+            return Ok(None);
+        }
+        let rows = <Self as AsyncQuery<SchemaRows>>::query(
+            self,
+            &get_snapshot_query(migration_table_name),
+        )
+        .await
+        .migration_err(|| "error getting schema snapshot", || [].into_iter())?;
+
+        rows.into_iter()
+            .next()
+            .map(|mut row| {
+                let snapshot = row.pop().flatten().unwrap_or_default();
+                let version = row.pop().flatten();
+                Ok((version, Schema::from_snapshot(&snapshot)?))
+            })
+            .transpose()
+    }
+
     async fn get_last_applied_migration(
         &mut self,
         migration_table_name: &str,
     ) -> Result<Option<Migration>, Error> {
-        let mut migrations = self
+        let migrations = self
             .query(Self::get_last_applied_migration_query(migration_table_name).as_ref())
             .await
             .migration_err(|| "error getting last applied migration", || [].into_iter())?;
 
-        Ok(migrations.pop())
+        Ok(migrations.into_iter().max())
     }
 
     async fn get_applied_migrations(
@@ -124,7 +269,7 @@ where
 
         let migrations = verify_migrations(
             applied_migrations,
-            migrations.to_vec(),
+            select_dialect_variants(migrations, self.dialect()),
             abort_divergent,
             abort_missing,
         )?;
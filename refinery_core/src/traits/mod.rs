@@ -3,26 +3,164 @@ use time::format_description::well_known::Rfc3339;
 pub mod r#async;
 pub mod sync;
 
+use std::collections::BTreeSet;
+
+use crate::config::ConfigDbType;
 use crate::runner::Type;
-use crate::util::SchemaVersion;
+#[cfg(feature = "toml")]
+use crate::schema::{Schema, SchemaChange};
 use crate::{error::Kind, Error, Migration};
 
+// Selects the migrations to be run on a database of the given `dialect`:
+// - migrations written for that dialect replace the generic migration with the same version and name
+// - migrations written for other dialects are left out
+pub(crate) fn select_dialect_variants(
+    migrations: &[Migration],
+    dialect: Option<ConfigDbType>,
+) -> Vec<Migration> {
+    migrations
+        .iter()
+        .filter(|migration| match migration.dialect() {
+            Some(migration_dialect) => Some(migration_dialect) == dialect,
+            None => !migrations.iter().any(|variant| {
+                variant.dialect().is_some()
+                    && variant.dialect() == dialect
+                    && variant.version() == migration.version()
+                    && variant.name() == migration.name()
+            }),
+        })
+        .cloned()
+        .collect()
+}
+
+// Finds the migration a `depends_on` entry of the metadata refers to, named in the `{1}[U|V|R]__{2}` format
+fn find_dependency<'m>(migrations: &'m [Migration], dependency: &str) -> Option<&'m Migration> {
+    migrations
+        .iter()
+        .find(|migration| migration.to_string() == dependency)
+}
+
+// Orders migrations so that every migration comes after the migrations it depends on,
+// migrations are otherwise ordered by version. Returns Error if:
+// - a migration depends on a migration that can't be found
+// - migrations depend on each other in a cycle
+fn order_migrations(mut migrations: Vec<Migration>) -> Result<Vec<Migration>, Error> {
+    migrations.sort();
+
+    let mut dependencies = Vec::with_capacity(migrations.len());
+    for migration in migrations.iter() {
+        let positions = migration
+            .metadata()
+            .depends_on
+            .iter()
+            .map(|dependency| {
+                migrations
+                    .iter()
+                    .position(|m| m.to_string() == *dependency)
+                    .ok_or_else(|| {
+                        Error::new(
+                            Kind::MissingDependency(migration.clone(), dependency.clone()),
+                            None,
+                        )
+                    })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        dependencies.push(positions);
+    }
+
+    let mut dependents = vec![Vec::new(); migrations.len()];
+    for (index, positions) in dependencies.iter().enumerate() {
+        for &position in positions {
+            dependents[position].push(index);
+        }
+    }
+
+    // migrations whose dependencies are all ordered, the lowest version is picked first
+    let mut pending = dependencies.iter().map(Vec::len).collect::<Vec<_>>();
+    let mut ready = (0..migrations.len())
+        .filter(|&index| pending[index] == 0)
+        .collect::<BTreeSet<_>>();
+    let mut ordered = vec![false; migrations.len()];
+    let mut order = Vec::with_capacity(migrations.len());
+    while let Some(index) = ready.pop_first() {
+        ordered[index] = true;
+        order.push(index);
+        for &dependent in &dependents[index] {
+            pending[dependent] -= 1;
+            if pending[dependent] == 0 {
+                ready.insert(dependent);
+            }
+        }
+    }
+
+    if let Some(mut index) = ordered.iter().position(|ordered| !ordered) {
+        // every migration left out depends on another one left out, following them leads to the cycle
+        let mut visited = Vec::new();
+        while !visited.contains(&index) {
+            visited.push(index);
+            index = dependencies[index]
+                .iter()
+                .copied()
+                .find(|&dependency| !ordered[dependency])
+                .unwrap();
+        }
+        return Err(Error::new(
+            Kind::CyclicDependency(migrations[index].clone()),
+            None,
+        ));
+    }
+
+    let mut migrations = migrations.into_iter().map(Some).collect::<Vec<_>>();
+    Ok(order
+        .into_iter()
+        // safe to call unwrap as every migration is ordered once
+        .map(|index| migrations[index].take().unwrap())
+        .collect())
+}
+
 // Verifies applied and to be applied migrations returning Error if:
 // - `abort_divergent` is true and there are applied migrations with a different name and checksum but same version as a migration to be applied.
 // - `abort_missing` is true and there are applied migrations that are missing on the file system
 // - there are repeated migrations with the same version to be applied
+// - a migration depends on a missing migration, or migrations depend on each other in a cycle
+// - an applied migration depends on a migration that isn't applied
+// - `abort_missing` is true and the database is behind a baseline whose squashed migrations were removed
+// migrations to be applied are returned ordered by their dependencies, and then by version,
+// migrations with dependencies aren't missing when their version is below the current one.
+// A baseline covers the migrations up to its version, it's only applied on an empty database,
+// in place of those migrations, and applied migrations it covers may be missing from the file system,
+// once applied the migrations it covers are left out even when they're still on the file system
 pub(crate) fn verify_migrations(
-    applied: Vec<Migration>,
-    mut migrations: Vec<Migration>,
+    mut applied: Vec<Migration>,
+    migrations: Vec<Migration>,
     abort_divergent: bool,
     abort_missing: bool,
 ) -> Result<Vec<Migration>, Error> {
-    migrations.sort();
+    let migrations = order_migrations(migrations)?;
+    // dot separated versions are stored as text, and therefore not ordered by the database
+    applied.sort();
+    let baseline = migrations
+        .iter()
+        .filter(|m| m.prefix() == &Type::Baseline)
+        .max()
+        .cloned();
 
     for app in applied.iter() {
         // iterate applied migrations on database and assert all migrations
-        // applied on database exist on the file system and have the same checksum
-        match migrations.iter().find(|m| m.version() == app.version()) {
+        // applied on database exist on the file system and have the same checksum,
+        // a baseline only stands for the applied migration with its version when it was applied itself
+        let found = migrations
+            .iter()
+            .find(|m| m.same_version(app) && m.name() == app.name())
+            .or_else(|| {
+                migrations
+                    .iter()
+                    .find(|m| m.same_version(app) && m.prefix() != &Type::Baseline)
+            });
+        match found {
+            None if baseline.as_ref().is_some_and(|baseline| app <= baseline) => {
+                log::debug!("applied migration {} is covered by baseline", app);
+            }
             None => {
                 if abort_missing {
                     return Err(Error::new(Kind::MissingVersion(app.clone()), None));
@@ -31,6 +169,17 @@ pub(crate) fn verify_migrations(
                 }
             }
             Some(migration) => {
+                for dependency in &migration.metadata().depends_on {
+                    // safe to call unwrap as dependencies were found when ordering migrations
+                    let dependency = find_dependency(&migrations, dependency).unwrap();
+                    if !applied.iter().any(|app| app.same_version(dependency)) {
+                        return Err(Error::new(
+                            Kind::UnappliedDependency(migration.clone(), dependency.clone()),
+                            None,
+                        ));
+                    }
+                }
+
                 if migration != app {
                     if abort_divergent {
                         return Err(Error::new(
@@ -50,31 +199,75 @@ pub(crate) fn verify_migrations(
         }
     }
 
-    let current: SchemaVersion = match applied.last() {
+    let current = match applied.last() {
         Some(last) => {
-            log::info!("current version: {}", last.version());
-            last.version() as SchemaVersion
+            log::info!("current version: {}", last.version_str());
+            Some(last)
         }
         None => {
             log::info!("schema history table is empty, going to apply all migrations");
-            // use -1 as versions might start with 0
-            -1
+            None
         }
     };
 
+    if let (Some(current), Some(baseline)) = (current, &baseline) {
+        // the migrations between the current version and the baseline must still exist to be applied
+        if current < baseline
+            && !migrations
+                .iter()
+                .any(|m| m.prefix() != &Type::Baseline && m > current && m <= baseline)
+        {
+            if abort_missing {
+                return Err(Error::new(Kind::MissingVersion(baseline.clone()), None));
+            } else {
+                log::error!(target: "refinery_core::traits::missing", "database at version {} can't be brought up to baseline {}", current.version_str(), baseline);
+            }
+        }
+    }
+
+    // a baseline applied to the database covers the migrations up to its version, which may still be on the file system
+    let applied_baseline = migrations
+        .iter()
+        .filter(|m| {
+            m.prefix() == &Type::Baseline
+                && applied
+                    .iter()
+                    .any(|app| app.same_version(m) && app.name() == m.name())
+        })
+        .max()
+        .cloned();
+
     let mut to_be_applied = Vec::new();
     // iterate all migration files found on file system and assert that there are not migrations missing:
     // migrations which its version is inferior to the current version on the database, yet were not applied.
-    // select to be applied all migrations with version greater than current
+    // select to be applied all migrations with version greater than current, and the migrations with
+    // dependencies, which are ordered by them and may therefore be added below the current version
     for migration in migrations.into_iter() {
-        if !applied
-            .iter()
-            .any(|app| app.version() == migration.version())
-        {
+        if applied_baseline.as_ref().is_some_and(|applied_baseline| {
+            migration.prefix() != &Type::Baseline && migration <= *applied_baseline
+        }) {
+            log::debug!("migration {} is covered by applied baseline", migration);
+            continue;
+        }
+
+        if let Some(baseline) = &baseline {
+            let skipped = if migration.prefix() == &Type::Baseline {
+                current.is_some() || migration != *baseline
+            } else {
+                current.is_none() && migration <= *baseline
+            };
+            if skipped {
+                log::debug!("skipping migration {} in favour of baseline", migration);
+                continue;
+            }
+        }
+
+        if !applied.iter().any(|app| app.same_version(&migration)) {
             if to_be_applied.contains(&migration) {
                 return Err(Error::new(Kind::RepeatedVersion(migration), None));
             } else if migration.prefix() == &Type::Versioned
-                && current >= migration.version() as SchemaVersion
+                && migration.metadata().depends_on.is_empty()
+                && current.is_some_and(|current| *current >= migration)
             {
                 if abort_missing {
                     return Err(Error::new(Kind::MissingVersion(migration), None));
@@ -92,12 +285,38 @@ pub(crate) fn verify_migrations(
     Ok(to_be_applied)
 }
 
+// Compares the `current` schema of the database with the snapshot recorded after the last migration,
+// returning Error if no snapshot was recorded, or if it was recorded before the last applied migration
+#[cfg(feature = "toml")]
+pub(crate) fn detect_drift(
+    snapshot: Option<(Option<String>, Schema)>,
+    last_applied: Option<Migration>,
+    current: &Schema,
+) -> Result<Vec<SchemaChange>, Error> {
+    let (version, snapshot) = snapshot.ok_or_else(|| Error::new(Kind::MissingSnapshot, None))?;
+    let last_version = last_applied.map(|migration| migration.version_str());
+    if version != last_version {
+        return Err(Error::new(
+            Kind::OutdatedSnapshot(
+                version.unwrap_or_else(|| "none".to_string()),
+                last_version.unwrap_or_else(|| "none".to_string()),
+            ),
+            None,
+        ));
+    }
+    Ok(snapshot.diff(current))
+}
+
 pub(crate) fn insert_migration_query(migration: &Migration, migration_table_name: &str) -> String {
     format!(
         "INSERT INTO {} (version, name, applied_on, checksum) VALUES ({}, '{}', '{}', '{}')",
         // safe to call unwrap as we just converted it to applied, and we are sure it can be formatted according to RFC 33339
         migration_table_name,
-        migration.version(),
+        if cfg!(feature = "dotted-versions") {
+            format!("'{}'", migration.version_str())
+        } else {
+            migration.version().to_string()
+        },
         migration.name(),
         migration.applied_on().unwrap().format(&Rfc3339).unwrap(),
         migration.checksum()
@@ -116,7 +335,9 @@ pub(crate) fn assert_migrations_table_query(migration_table_name: &str) -> Strin
         .replace("%MIGRATION_TABLE_NAME%", migration_table_name)
         .replace(
             "%VERSION_TYPE%",
-            if cfg!(feature = "int8-versions") {
+            if cfg!(feature = "dotted-versions") {
+                "VARCHAR(255)"
+            } else if cfg!(feature = "int8-versions") {
                 "int8"
             } else {
                 "int4"
@@ -127,15 +348,23 @@ pub(crate) fn assert_migrations_table_query(migration_table_name: &str) -> Strin
 pub(crate) const GET_APPLIED_MIGRATIONS_QUERY: &str = "SELECT version, name, applied_on, checksum \
     FROM %MIGRATION_TABLE_NAME% ORDER BY version ASC;";
 
+#[cfg(not(feature = "dotted-versions"))]
 pub(crate) const GET_LAST_APPLIED_MIGRATION_QUERY: &str =
     "SELECT version, name, applied_on, checksum
     FROM %MIGRATION_TABLE_NAME% WHERE version=(SELECT MAX(version) from %MIGRATION_TABLE_NAME%)";
 
+// dot separated versions are stored as text, which the database can't order by,
+// so the last applied migration is picked from all of them
+#[cfg(feature = "dotted-versions")]
+pub(crate) const GET_LAST_APPLIED_MIGRATION_QUERY: &str = GET_APPLIED_MIGRATIONS_QUERY;
+
 pub(crate) const DEFAULT_MIGRATION_TABLE_NAME: &str = "refinery_schema_history";
 
 #[cfg(test)]
 mod tests {
-    use super::{verify_migrations, Kind, Migration};
+    use super::{select_dialect_variants, verify_migrations, Kind, Migration};
+    use crate::config::ConfigDbType;
+    use crate::runner::MigrationMetadata;
 
     fn get_migrations() -> Vec<Migration> {
         let migration1 = Migration::unapplied(
@@ -326,4 +555,365 @@ mod tests {
             _ => panic!("failed test"),
         }
     }
+
+    #[test]
+    fn select_dialect_variants_replaces_generic_migration() {
+        let mut migrations = get_migrations();
+        let postgres = Migration::unapplied(
+            "4V__add_year_field_to_cars.postgres",
+            "ALTER TABLE cars ADD COLUMN year INTEGER;",
+        )
+        .unwrap();
+        let mysql = Migration::unapplied(
+            "4V__add_year_field_to_cars.mysql",
+            "ALTER TABLE cars ADD COLUMN year INT;",
+        )
+        .unwrap();
+        migrations.push(postgres.clone());
+        migrations.push(mysql.clone());
+
+        let selected = select_dialect_variants(&migrations, Some(ConfigDbType::Postgres));
+        assert_eq!(4, selected.len());
+        assert_eq!(postgres, selected[3]);
+
+        let selected = select_dialect_variants(&migrations, Some(ConfigDbType::Sqlite));
+        assert_eq!(&migrations[..4], selected.as_slice());
+
+        let selected = select_dialect_variants(&migrations, None);
+        assert_eq!(&migrations[..4], selected.as_slice());
+
+        let result = verify_migrations(
+            vec![],
+            select_dialect_variants(&migrations, Some(ConfigDbType::Mysql)),
+            true,
+            true,
+        )
+        .unwrap();
+        assert_eq!(4, result.len());
+        assert_eq!(mysql, result[3]);
+    }
+
+    #[test]
+    #[cfg(feature = "dotted-versions")]
+    fn verify_migrations_orders_dotted_versions() {
+        let migration1 = Migration::unapplied("1.2V__first", "SELECT 1;").unwrap();
+        let migration2 = Migration::unapplied("1.10V__second", "SELECT 1;").unwrap();
+        let migration3 = Migration::unapplied("2V__third", "SELECT 1;").unwrap();
+        let migration4 = Migration::unapplied("2.0.1V__fourth", "SELECT 1;").unwrap();
+
+        let migrations = vec![
+            migration4.clone(),
+            migration2.clone(),
+            migration3.clone(),
+            migration1.clone(),
+        ];
+        // as returned by a database ordering the versions as text
+        let applied = vec![migration2.clone(), migration1.clone()];
+
+        let result = verify_migrations(applied, migrations, true, true).unwrap();
+        assert_eq!(vec![migration3, migration4], result);
+    }
+
+    #[test]
+    #[cfg(feature = "dotted-versions")]
+    fn verify_migrations_fails_on_missing_dotted_version() {
+        let migration1 = Migration::unapplied("1.2V__first", "SELECT 1;").unwrap();
+        let migration2 = Migration::unapplied("1.10V__second", "SELECT 1;").unwrap();
+        let migration3 = Migration::unapplied("1.3V__third", "SELECT 1;").unwrap();
+
+        let migrations = vec![migration1.clone(), migration2.clone(), migration3.clone()];
+        let applied = vec![migration1, migration2];
+
+        let err = verify_migrations(applied, migrations, true, true).unwrap_err();
+        match err.kind() {
+            Kind::MissingVersion(missing) => {
+                assert_eq!(&migration3, missing);
+                assert_eq!("1.3V__third", missing.to_string());
+            }
+            _ => panic!("failed test"),
+        }
+    }
+
+    fn depending_on(migration: Migration, depends_on: &[&str]) -> Migration {
+        migration.set_metadata(MigrationMetadata {
+            depends_on: depends_on.iter().map(|d| d.to_string()).collect(),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn verify_migrations_orders_by_dependencies() {
+        let migrations = get_migrations();
+        let migration1 = migrations[0].clone();
+        let migration2 = depending_on(migrations[1].clone(), &["4V__add_year_field_to_cars"]);
+        let migration3 = migrations[2].clone();
+        let migration4 = migrations[3].clone();
+
+        let result = verify_migrations(
+            vec![],
+            vec![
+                migration4.clone(),
+                migration3.clone(),
+                migration2.clone(),
+                migration1.clone(),
+            ],
+            true,
+            true,
+        )
+        .unwrap();
+        assert_eq!(vec![migration1, migration3, migration4, migration2], result);
+    }
+
+    #[test]
+    fn verify_migrations_fails_on_missing_dependency() {
+        let migration1 = depending_on(get_migrations()[0].clone(), &["10V__users"]);
+
+        let err = verify_migrations(vec![], vec![migration1.clone()], true, true).unwrap_err();
+        match err.kind() {
+            Kind::MissingDependency(migration, dependency) => {
+                assert_eq!(&migration1, migration);
+                assert_eq!("10V__users", dependency);
+            }
+            _ => panic!("failed test"),
+        }
+    }
+
+    #[test]
+    fn verify_migrations_fails_on_dependency_cycle() {
+        let migrations = get_migrations();
+        let migration1 = migrations[0].clone();
+        let migration2 = depending_on(migrations[1].clone(), &["3V__add_brand_to_cars_table"]);
+        let migration3 = depending_on(migrations[2].clone(), &["2V__add_cars_and_motos_table"]);
+        let migration4 = depending_on(migrations[3].clone(), &["2V__add_cars_and_motos_table"]);
+
+        let err = verify_migrations(
+            vec![],
+            vec![migration1, migration2.clone(), migration3, migration4],
+            true,
+            true,
+        )
+        .unwrap_err();
+        match err.kind() {
+            Kind::CyclicDependency(migration) => {
+                assert!(migration.version() == 2 || migration.version() == 3)
+            }
+            _ => panic!("failed test"),
+        }
+    }
+
+    #[test]
+    fn verify_migrations_fails_on_unapplied_dependency() {
+        let migrations = get_migrations();
+        let migration1 = migrations[0].clone();
+        let migration2 = depending_on(migrations[1].clone(), &["4V__add_year_field_to_cars"]);
+        let migration4 = migrations[3].clone();
+
+        let err = verify_migrations(
+            vec![migration1.clone(), migration2.clone()],
+            vec![migration1, migration2.clone(), migration4.clone()],
+            true,
+            true,
+        )
+        .unwrap_err();
+        match err.kind() {
+            Kind::UnappliedDependency(migration, dependency) => {
+                assert_eq!(&migration2, migration);
+                assert_eq!(&migration4, dependency);
+            }
+            _ => panic!("failed test"),
+        }
+    }
+
+    #[test]
+    fn verify_migrations_returns_unapplied_with_dependencies_below_current() {
+        let migrations = get_migrations();
+        let migration1 = migrations[0].clone();
+        let migration2 = depending_on(migrations[1].clone(), &["4V__add_year_field_to_cars"]);
+        let migration3 = migrations[2].clone();
+        let migration4 = migrations[3].clone();
+
+        let result = verify_migrations(
+            vec![migration1.clone(), migration3.clone(), migration4.clone()],
+            vec![migration1, migration2.clone(), migration3, migration4],
+            true,
+            true,
+        )
+        .unwrap();
+        assert_eq!(vec![migration2], result);
+    }
+
+    #[test]
+    fn select_target_leaves_out_migrations_depending_on_migrations_past_target() {
+        use super::sync::select_target;
+        use crate::Target;
+
+        let migrations = get_migrations();
+        let migration1 = migrations[0].clone();
+        let migration2 = depending_on(migrations[1].clone(), &["4V__add_year_field_to_cars"]);
+        let migration3 = migrations[2].clone();
+        let migration4 = migrations[3].clone();
+        let ordered = verify_migrations(
+            vec![],
+            vec![
+                migration1.clone(),
+                migration2,
+                migration3.clone(),
+                migration4,
+            ],
+            true,
+            true,
+        )
+        .unwrap();
+
+        assert_eq!(
+            vec![migration1.clone(), migration3.clone()],
+            select_target(ordered.clone(), Target::Version(3)).unwrap()
+        );
+        assert_eq!(
+            vec![migration1, migration3],
+            select_target(ordered, Target::FakeVersion(3)).unwrap()
+        );
+    }
+
+    #[cfg(feature = "dotted-versions")]
+    #[test]
+    fn select_target_rejects_dotted_versions_of_the_target() {
+        use super::sync::select_target;
+        use crate::Target;
+
+        let migration1 = Migration::unapplied("1V__initial", "CREATE TABLE a (id int);").unwrap();
+        let migration2 = Migration::unapplied("2V__b", "CREATE TABLE b (id int);").unwrap();
+        let migration2_1 = Migration::unapplied("2.1V__c", "CREATE TABLE c (id int);").unwrap();
+        let migrations = vec![migration1.clone(), migration2, migration2_1];
+
+        assert_eq!(
+            vec![migration1],
+            select_target(migrations.clone(), Target::Version(1)).unwrap()
+        );
+        let err = select_target(migrations, Target::Version(2)).unwrap_err();
+        assert!(
+            matches!(err.kind(), Kind::AmbiguousTarget(migration, 2) if migration.version_str() == "2.1")
+        );
+    }
+
+    fn baseline() -> Migration {
+        Migration::unapplied(
+            "2B__baseline",
+            "CREATE TABLE persons (id int, name varchar(255), city varchar(255));
+             CREATE TABLE cars (id int, name varchar(255));
+             CREATE TABLE motos (id int, name varchar(255));",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn verify_migrations_applies_baseline_on_empty_database() {
+        let mut migrations = get_migrations();
+        migrations.push(baseline());
+        let result = verify_migrations(vec![], migrations.clone(), true, true).unwrap();
+        assert_eq!(
+            vec![baseline(), migrations[2].clone(), migrations[3].clone()],
+            result
+        );
+    }
+
+    #[test]
+    fn verify_migrations_skips_baseline_on_existing_database() {
+        let migrations = get_migrations();
+        let applied = vec![migrations[0].clone(), migrations[1].clone()];
+        let result = verify_migrations(
+            applied,
+            vec![baseline(), migrations[2].clone(), migrations[3].clone()],
+            true,
+            true,
+        )
+        .unwrap();
+        assert_eq!(vec![migrations[2].clone(), migrations[3].clone()], result);
+    }
+
+    #[test]
+    fn verify_migrations_skips_migrations_covered_by_applied_baseline() {
+        let mut migrations = get_migrations();
+        migrations.push(baseline());
+        let result = verify_migrations(vec![baseline()], migrations.clone(), true, true).unwrap();
+        assert_eq!(vec![migrations[2].clone(), migrations[3].clone()], result);
+    }
+
+    #[test]
+    fn verify_migrations_fails_behind_baseline_without_squashed_migrations() {
+        let migrations = get_migrations();
+        let err = verify_migrations(
+            vec![migrations[0].clone()],
+            vec![baseline(), migrations[2].clone(), migrations[3].clone()],
+            true,
+            true,
+        )
+        .unwrap_err();
+        match err.kind() {
+            Kind::MissingVersion(migration) => assert_eq!(&baseline(), migration),
+            _ => panic!("failed test"),
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "toml")]
+    fn detect_drift_lists_changes_since_snapshot() {
+        use super::detect_drift;
+        use crate::schema::{Column, Schema, SchemaChange, Table};
+
+        let column = Column {
+            name: "name".to_string(),
+            data_type: "text".to_string(),
+            nullable: true,
+            default: None,
+        };
+        let snapshot = Schema {
+            tables: vec![Table {
+                name: "persons".to_string(),
+                columns: vec![column.clone()],
+                ..Default::default()
+            }],
+        };
+        let mut current = snapshot.clone();
+        current.tables[0].columns[0].nullable = false;
+
+        let changes = detect_drift(
+            Some((Some("1".to_string()), snapshot)),
+            Some(get_migrations()[0].clone()),
+            &current,
+        )
+        .unwrap();
+        assert_eq!(
+            vec![SchemaChange::ChangedColumn {
+                table: "persons".to_string(),
+                before: column,
+                after: current.tables[0].columns[0].clone(),
+            }],
+            changes
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "toml")]
+    fn detect_drift_fails_on_outdated_snapshot() {
+        use super::detect_drift;
+        use crate::schema::Schema;
+
+        let err = detect_drift(
+            Some((Some("1".to_string()), Schema::default())),
+            Some(get_migrations()[1].clone()),
+            &Schema::default(),
+        )
+        .unwrap_err();
+        match err.kind() {
+            Kind::OutdatedSnapshot(recorded, current) => {
+                assert_eq!("1", recorded);
+                assert_eq!("2", current);
+            }
+            _ => panic!("failed test"),
+        }
+
+        let err = detect_drift(None, None, &Schema::default()).unwrap_err();
+        assert!(matches!(err.kind(), Kind::MissingSnapshot));
+    }
 }
@@ -1,7 +1,14 @@
+use crate::config::ConfigDbType;
+use crate::error::Kind;
 use crate::error::WrapMigrationError;
+#[cfg(feature = "toml")]
+use crate::schema::{get_snapshot_query, record_snapshot_queries, snapshot_table_exists_query};
+use crate::schema::{
+    introspection_queries, uncaptured_object, uncaptured_objects_queries, Schema, SchemaRows,
+};
 use crate::traits::{
-    insert_migration_query, verify_migrations, GET_APPLIED_MIGRATIONS_QUERY,
-    GET_LAST_APPLIED_MIGRATION_QUERY,
+    insert_migration_query, select_dialect_variants, verify_migrations,
+    GET_APPLIED_MIGRATIONS_QUERY, GET_LAST_APPLIED_MIGRATION_QUERY,
 };
 use crate::{Error, Migration, Report, Target};
 
@@ -12,6 +19,15 @@ pub trait Transaction {
         &mut self,
         queries: T,
     ) -> Result<usize, Self::Error>;
+
+    // Executes the queries without wrapping them in a transaction, for migrations that can't run inside one.
+    // Defaults to `execute` for connections that can only run queries inside a transaction
+    fn execute_outside_transaction<S: AsRef<str>, T: Iterator<Item = S>>(
+        &mut self,
+        queries: T,
+    ) -> Result<usize, Self::Error> {
+        self.execute(queries)
+    }
 }
 
 pub trait Query<T>: Transaction {
@@ -147,6 +163,8 @@ impl<'mtn> MigrateReusableIterator<'mtn>
                 { f }
                 let strings_only = migrations_filtered_by_whether_apply.map(constrain(move |(_, (reference, owned)): (&Migration, (Option<&str>, Option<String>))| [reference.map(Cow::Borrowed),owned.map(Cow::Owned)])).flatten().flatten();
                 let migrations_display = itertools::Itertools::format(migrations_to_apply(), ", ");
+                // the whole batch is a single item
+                self.iter_state = self.args.migrations.len() as u32;
 
                 Some(
                         MigrateReusableIteratorItem {
@@ -170,7 +188,8 @@ impl<'mtn> MigrateReusableIterator<'mtn>
                                 Some(
                                     MigrateReusableIteratorItem {
                                         log_before_tx: LogData {level: log::Level::Info, msg: "applying migration"},
-                                        applied_migrations: migrations_applied_for_logging,
+                                        // taken again, as iter_state moved on to this migration since
+                                        applied_migrations: self.args.migrations[..self.iter_state as usize].iter().filter(filter),
                                         result: MigrateReusableResult::Itemized {
                                             sql: migration_sql,
                                             current_migration: current_migration_struct,
@@ -182,7 +201,7 @@ impl<'mtn> MigrateReusableIterator<'mtn>
                                 Some(
                                     MigrateReusableIteratorItem {
                                         log_before_tx: LogData {level: log::Level::Debug, msg: "applied migration, writing state to db"},
-                                        applied_migrations: migrations_applied_for_logging,
+                                        applied_migrations: self.args.migrations[..self.iter_state as usize].iter().filter(filter),
 
                                         result: MigrateReusableResult::ItemizedMetaInsert {
                                             sql: migrations_table_insert_sql,
@@ -213,6 +232,10 @@ impl<'mtn> MigrateReusableIterator<'mtn>
         next_maybe_batched_or_itemized
     }
     pub(crate) fn applied(self) -> Vec<Migration> {
+        // a fake run only records the migrations in the history table, it applies none
+        if matches!(self.args.target, Target::Fake | Target::FakeVersion(_)) {
+            return Vec::new();
+        }
         self.args.migrations.into_iter().filter(|migration| migration_whether_apply(migration, self.args.target)).collect::<Vec<_>>()
     }
 }
@@ -228,6 +251,70 @@ impl<'mtn> MigrateReusableIterator<'mtn> {
     }
 }
 
+// Leaves out the migrations past the target version, and the migrations depending on them,
+// which would otherwise be applied without their dependencies. Migrations come ordered by their dependencies
+pub(crate) fn select_target(
+    migrations: Vec<Migration>,
+    target: Target,
+) -> Result<Vec<Migration>, Error> {
+    // targets only take a major version, which can't tell i.e. 2.1 from 2.2 apart
+    if let Target::Version(version) | Target::FakeVersion(version) = target {
+        if let Some(migration) = migrations.iter().find(|migration| {
+            migration.version() == version && !migration.sub_versions().is_empty()
+        }) {
+            return Err(Error::new(
+                Kind::AmbiguousTarget(migration.clone(), version),
+                None,
+            ));
+        }
+    }
+    let mut skipped = Vec::new();
+    Ok(migrations
+        .into_iter()
+        .filter(|migration| {
+            if !migration_whether_apply(migration, target) {
+                skipped.push(migration.to_string());
+                return false;
+            }
+            match migration
+                .metadata()
+                .depends_on
+                .iter()
+                .find(|dependency| skipped.contains(dependency))
+            {
+                Some(dependency) => {
+                    log::info!(
+                        "skipping migration: {migration}, as its dependency {dependency} is past the target",
+                    );
+                    skipped.push(migration.to_string());
+                    false
+                }
+                None => true,
+            }
+        })
+        .collect())
+}
+
+// Migrations that can't run inside a transaction can't be applied along with others in a single one
+pub(crate) fn assert_grouped_transactional(
+    migrations: &[Migration],
+    target: Target,
+    batched: bool,
+) -> Result<(), Error> {
+    if !batched || matches!(target, Target::Fake | Target::FakeVersion(_)) {
+        return Ok(());
+    }
+    match migrations.iter().find(|migration| {
+        !migration.metadata().transactional && migration_whether_apply(migration, target)
+    }) {
+        Some(migration) => Err(Error::new(
+            Kind::NonTransactionalGrouped(migration.clone()),
+            None,
+        )),
+        None => Ok(()),
+    }
+}
+
 pub(crate) fn migrate_reusable<'mtn>(
     mut migrations: Vec<Migration>,
     target: Target,
@@ -270,6 +357,8 @@ pub fn migrate<T: Transaction>(
     migration_table_name: &str,
     batched: bool,
 ) -> Result<Report, Error> {
+    let migrations = select_target(migrations, target)?;
+    assert_grouped_transactional(&migrations, target, batched)?;
     let mut iter = migrate_reusable(migrations, target, migration_table_name, batched);
     while let Some(next) = iter.next() {
         match next.result {
@@ -281,9 +370,14 @@ pub fn migrate<T: Transaction>(
             },
             MigrateReusableResult::Itemized { sql, current_migration } => {
                 log::log!(next.log_before_tx.level, "{}: {current_migration}", next.log_before_tx.msg);
-                transaction
-                    .execute([sql].into_iter())
-                    .migration_err(|| format!("error applying single migration: {current_migration}"), || next.applied_migrations.cloned())?;
+                let result = if current_migration.metadata().transactional {
+                    transaction.execute([sql].into_iter())
+                } else {
+                    transaction.execute_outside_transaction([sql].into_iter())
+                };
+                result
+                    .migration_err(|| format!("error applying single migration: {current_migration}"), || next.applied_migrations.cloned())
+                    .map_err(|err| err.locate(sql))?;
             }
             MigrateReusableResult::ItemizedMetaInsert { sql, current_migration } => {
                 log::log!(next.log_before_tx.level, "{}: {current_migration}", next.log_before_tx.msg);
@@ -314,6 +408,11 @@ where
         GET_APPLIED_MIGRATIONS_QUERY.replace("%MIGRATION_TABLE_NAME%", migration_table_name)
     }
 
+    // The database this connection talks to, used to choose between dialect specific migration variants
+    fn dialect(&self) -> Option<ConfigDbType> {
+        None
+    }
+
     fn assert_migrations_table(&mut self, migration_table_name: &str) -> Result<usize, Error> {
         // Needed cause some database vendors like Mssql have a non sql standard way of checking the migrations table,
         // though on this case it's just to be consistent with the async trait `AsyncMigrate`
@@ -323,15 +422,121 @@ where
         .migration_err(|| "error asserting migrations table", || [].into_iter())
     }
 
+    // Introspects the schema the migrations created, only available for connections that can read schema rows
+    fn introspect_schema(&mut self, migration_table_name: &str) -> Result<Schema, Error>
+    where
+        Self: Query<SchemaRows>,
+    {
+        let dialect = self
+            .dialect()
+            .ok_or_else(|| Error::new(Kind::UnknownDialect, None))?;
+
+        let [columns, primary_keys, indexes] = introspection_queries(dialect);
+        let columns = <Self as Query<SchemaRows>>::query(self, columns)
+            .migration_err(|| "error introspecting schema", || [].into_iter())?;
+        let primary_keys = <Self as Query<SchemaRows>>::query(self, primary_keys)
+            .migration_err(|| "error introspecting schema", || [].into_iter())?;
+        let indexes = <Self as Query<SchemaRows>>::query(self, indexes)
+            .migration_err(|| "error introspecting schema", || [].into_iter())?;
+
+        Ok(Schema::from_rows(
+            columns,
+            primary_keys,
+            indexes,
+            migration_table_name,
+        ))
+    }
+
+    // Lists the objects of the schema a baseline can't hold, i.e. views, triggers or foreign keys
+    fn uncaptured_schema_objects(&mut self) -> Result<Vec<String>, Error>
+    where
+        Self: Query<SchemaRows>,
+    {
+        let dialect = self
+            .dialect()
+            .ok_or_else(|| Error::new(Kind::UnknownDialect, None))?;
+
+        let mut objects = Vec::new();
+        for query in uncaptured_objects_queries(dialect) {
+            let rows = <Self as Query<SchemaRows>>::query(self, query)
+                .migration_err(|| "error introspecting schema", || [].into_iter())?;
+            objects.extend(rows.iter().map(|row| uncaptured_object(row)));
+        }
+        Ok(objects)
+    }
+
+    // Records a snapshot of the schema along with the version of the last applied migration, to detect drift later on
+    #[cfg(feature = "toml")]
+    fn record_schema_snapshot(&mut self, migration_table_name: &str) -> Result<Schema, Error>
+    where
+        Self: Query<SchemaRows>,
+    {
+        let dialect = self
+            .dialect()
+            .ok_or_else(|| Error::new(Kind::UnknownDialect, None))?;
+
+        self.assert_migrations_table(migration_table_name)?;
+        let version = self
+            .get_last_applied_migration(migration_table_name)?
+            .map(|migration| migration.version_str());
+        let schema = self.introspect_schema(migration_table_name)?;
+        let queries = record_snapshot_queries(
+            dialect,
+            migration_table_name,
+            version.as_deref(),
+            &schema.to_snapshot()?,
+        );
+        self.execute(queries.into_iter())
+            .migration_err(|| "error recording schema snapshot", || [].into_iter())?;
+
+        Ok(schema)
+    }
+
+    // Get the recorded schema snapshot along with the version of the last migration applied when it was recorded
+    #[cfg(feature = "toml")]
+    fn get_schema_snapshot(
+        &mut self,
+        migration_table_name: &str,
+    ) -> Result<Option<(Option<String>, Schema)>, Error>
+    where
+        Self: Query<SchemaRows>,
+    {
+        let dialect = self
+            .dialect()
+            .ok_or_else(|| Error::new(Kind::UnknownDialect, None))?;
+
+        // the snapshot table is only created when recording a snapshot
+        let tables = <Self as Query<SchemaRows>>::query(
+            self,
+            &snapshot_table_exists_query(dialect, migration_table_name),
+        )
+        .migration_err(|| "error getting schema snapshot", || [].into_iter())?;
+        if tables.is_empty() {
+            return Ok(None);
+        }
+        let rows =
+            <Self as Query<SchemaRows>>::query(self, &get_snapshot_query(migration_table_name))
+                .migration_err(|| "error getting schema snapshot", || [].into_iter())?;
+
+        rows.into_iter()
+            .next()
+            .map(|mut row| {
+                let snapshot = row.pop().flatten().unwrap_or_default();
+                let version = row.pop().flatten();
+                Ok((version, Schema::from_snapshot(&snapshot)?))
+            })
+            .transpose()
+    }
+
     fn get_last_applied_migration(
         &mut self,
         migration_table_name: &str,
     ) -> Result<Option<Migration>, Error> {
-        let mut migrations = self
+        let migrations = self
             .query(Self::get_last_applied_migration_query(migration_table_name).as_str())
             .migration_err(|| "error getting last applied migration", || [].into_iter())?;
 
-        Ok(migrations.pop())
+        Ok(migrations.into_iter().max())
     }
 
     fn get_applied_migrations(
@@ -358,7 +563,7 @@ where
 
         let migrations = verify_migrations(
             applied_migrations,
-            migrations.to_vec(),
+            select_dialect_variants(migrations, self.dialect()),
             abort_divergent,
             abort_missing,
         )?;
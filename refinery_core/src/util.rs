@@ -1,5 +1,6 @@
+use crate::config::ConfigDbType;
 use crate::error::{Error, Kind};
-use crate::runner::Type;
+use crate::runner::{MigrationMetadata, Type};
 use crate::Migration;
 use regex::Regex;
 use std::ffi::OsStr;
@@ -12,25 +13,45 @@ pub type SchemaVersion = i32;
 #[cfg(feature = "int8-versions")]
 pub type SchemaVersion = i64;
 
-const STEM_RE: &str = r"^(\d+(?:\.\d+)?)([U|V|R])__(\w+)";
-
-/// Matches the stem of a migration file.
-fn file_stem_re() -> &'static Regex {
-    static RE: OnceLock<Regex> = OnceLock::new();
-    RE.get_or_init(|| Regex::new(STEM_RE).unwrap())
-}
-
-/// Matches the stem + extension of a SQL migration file.
-fn file_re_sql() -> &'static Regex {
-    static RE: OnceLock<Regex> = OnceLock::new();
-    RE.get_or_init(|| Regex::new([STEM_RE, r"\.sql$"].concat().as_str()).unwrap())
-}
-
-/// Matches the stem + extension of any migration file.
-fn file_re_all() -> &'static Regex {
-    static RE: OnceLock<Regex> = OnceLock::new();
-    RE.get_or_init(|| Regex::new([STEM_RE, r"\.(rs|sql)$"].concat().as_str()).unwrap())
-}
+// type of the version column on the migrations table, read by the drivers
+#[cfg(all(
+    not(feature = "dotted-versions"),
+    any(
+        feature = "mysql",
+        feature = "postgres",
+        feature = "rusqlite",
+        feature = "tokio-postgres",
+        feature = "mysql_async",
+        feature = "tiberius",
+        feature = "sqlx",
+        feature = "diesel-postgres",
+        feature = "diesel-mysql",
+        feature = "diesel-sqlite"
+    )
+))]
+pub(crate) type StoredVersion = SchemaVersion;
+#[cfg(all(
+    feature = "dotted-versions",
+    any(
+        feature = "mysql",
+        feature = "postgres",
+        feature = "rusqlite",
+        feature = "tokio-postgres",
+        feature = "mysql_async",
+        feature = "tiberius",
+        feature = "sqlx",
+        feature = "diesel-postgres",
+        feature = "diesel-mysql",
+        feature = "diesel-sqlite"
+    )
+))]
+pub(crate) type StoredVersion = String;
+
+const UP_SQL: &str = "up.sql";
+const DOWN_SQL: &str = "down.sql";
+const METADATA_FILE: &str = "migration.toml";
+
+const STEM_RE: &str = r"^(?P<version>\d+(?:\.\d+)*)(?P<prefix>[U|V|R|B])__(?P<name>\w+)(?:\.(?P<dialect>postgres|mysql|sqlite|mssql))?";
 
 /// enum containing the migration types used to search for migrations
 /// either just .sql files or both .sql and .rs
@@ -40,39 +61,217 @@ pub enum MigrationType {
 }
 
 impl MigrationType {
-    fn file_match_re(&self) -> &'static Regex {
+    fn matches_extension(&self, extension: &str) -> bool {
         match self {
-            MigrationType::All => file_re_all(),
-            MigrationType::Sql => file_re_sql(),
+            MigrationType::All => extension == "rs" || extension == "sql",
+            MigrationType::Sql => extension == "sql",
+        }
+    }
+}
+
+/// Parse a dot separated version, i.e. `2.1.3`, into its major version followed by the remaining parts.
+/// Versions with more than one part are only accepted with the `dotted-versions` feature.
+pub(crate) fn parse_version(version: &str) -> Result<(SchemaVersion, Vec<SchemaVersion>), Error> {
+    let mut parts = version.split('.').map(|part| {
+        part.parse::<SchemaVersion>()
+            .map_err(|_| Error::new(Kind::InvalidVersion, None))
+    });
+    // split always yields at least one part
+    let major = parts.next().unwrap()?;
+    let sub_versions = parts.collect::<Result<Vec<_>, _>>()?;
+
+    if !sub_versions.is_empty() && !cfg!(feature = "dotted-versions") {
+        return Err(Error::new(Kind::InvalidVersion, None));
+    }
+
+    Ok((major, sub_versions))
+}
+
+// the parts a migration filename stem is made of
+pub(crate) struct MigrationName {
+    pub(crate) prefix: Type,
+    pub(crate) version: SchemaVersion,
+    pub(crate) sub_versions: Vec<SchemaVersion>,
+    pub(crate) name: String,
+    pub(crate) dialect: Option<ConfigDbType>,
+}
+
+/// Describes how migration files are named, so that existing migrations can be adopted without renaming them.
+///
+/// A `NamingStrategy` is a regular expression matched against the migration filename stem,
+/// i.e. the file name without the `.sql` or `.rs` extension, with the following named captures:
+/// - `version`, required, the migration version
+/// - `name`, required, the migration name
+/// - `prefix`, optional, `V`, `U`, `R` or `B`, migrations are versioned when missing
+/// - `dialect`, optional, `postgres`, `mysql`, `sqlite` or `mssql`
+///
+/// The default strategy matches refinery's `{1}[U|V|R|B]__{2}` format, files named as `20240504090241_create_users.up.sql`
+/// can instead be matched with `^(?P<version>\d+)_(?P<name>\w+)\.up$`. Timestamp versions like this one don't fit
+/// in the default `i32` [`SchemaVersion`] and need the `int8-versions` feature.
+#[derive(Clone, Debug)]
+pub struct NamingStrategy {
+    stem_re: Regex,
+}
+
+impl NamingStrategy {
+    /// Create a new naming strategy from a regular expression, which must have the `version` and `name` named captures
+    pub fn new(pattern: &str) -> Result<NamingStrategy, Error> {
+        let stem_re = Regex::new(pattern)
+            .map_err(|err| Error::new(Kind::InvalidNamingPattern(err.to_string()), None))?;
+
+        for capture in ["version", "name"] {
+            if !stem_re.capture_names().any(|name| name == Some(capture)) {
+                return Err(Error::new(
+                    Kind::InvalidNamingPattern(format!("missing named capture `{capture}`")),
+                    None,
+                ));
+            }
+        }
+
+        Ok(NamingStrategy { stem_re })
+    }
+
+    /// Get the regular expression migration filename stems are matched against
+    pub fn pattern(&self) -> &str {
+        self.stem_re.as_str()
+    }
+
+    /// Parse a migration filename stem into a prefix, version, and name.
+    /// For dot separated versions, i.e. `2.1.3`, the returned version is the major one.
+    pub fn parse(&self, stem: &str) -> Result<(Type, SchemaVersion, String), Error> {
+        let migration_name = self.parse_parts(stem)?;
+        Ok((
+            migration_name.prefix,
+            migration_name.version,
+            migration_name.name,
+        ))
+    }
+
+    /// Convert a migration filename stem into refinery's default `{1}[U|V|R|B]__{2}` format,
+    /// i.e. `42V__create_users`, as expected by [`Migration::unapplied`]
+    pub fn canonical_name(&self, stem: &str) -> Result<String, Error> {
+        let migration_name = self.parse_parts(stem)?;
+        let mut canonical_name = std::iter::once(migration_name.version)
+            .chain(migration_name.sub_versions)
+            .map(|part| part.to_string())
+            .collect::<Vec<_>>()
+            .join(".");
+        canonical_name = format!(
+            "{canonical_name}{}__{}",
+            migration_name.prefix, migration_name.name
+        );
+        if let Some(dialect) = migration_name.dialect {
+            canonical_name = format!("{canonical_name}.{}", dialect_suffix(dialect));
         }
+
+        Ok(canonical_name)
+    }
+
+    pub(crate) fn parse_parts(&self, stem: &str) -> Result<MigrationName, Error> {
+        let captures = self
+            .stem_re
+            .captures(stem)
+            .ok_or_else(|| Error::new(Kind::InvalidName, None))?;
+        // both are checked to be present when building the strategy, but may be on an unmatched optional group
+        let version = captures
+            .name("version")
+            .ok_or_else(|| Error::new(Kind::InvalidName, None))?;
+        let name = captures
+            .name("name")
+            .ok_or_else(|| Error::new(Kind::InvalidName, None))?;
+        let (version, sub_versions) = parse_version(version.as_str())?;
+
+        let prefix = match captures.name("prefix").map(|prefix| prefix.as_str()) {
+            Some("V") | None => Type::Versioned,
+            Some("U") => Type::Unversioned,
+            Some("R") => Type::Rerunnable,
+            Some("B") => Type::Baseline,
+            Some(_) => return Err(Error::new(Kind::InvalidName, None)),
+        };
+
+        let dialect = match captures.name("dialect").map(|dialect| dialect.as_str()) {
+            None => None,
+            Some("postgres") => Some(ConfigDbType::Postgres),
+            Some("mysql") => Some(ConfigDbType::Mysql),
+            Some("sqlite") => Some(ConfigDbType::Sqlite),
+            Some("mssql") => Some(ConfigDbType::Mssql),
+            Some(_) => return Err(Error::new(Kind::InvalidName, None)),
+        };
+
+        Ok(MigrationName {
+            prefix,
+            version,
+            sub_versions,
+            name: name.as_str().into(),
+            dialect,
+        })
+    }
+
+    // whether a file name is a migration of the given type, the whole stem must be matched
+    fn matches_file(&self, file_name: &str, migration_type: &MigrationType) -> bool {
+        let Some((stem, extension)) = file_name.rsplit_once('.') else {
+            return false;
+        };
+
+        migration_type.matches_extension(extension) && self.matches_stem(stem)
+    }
+
+    fn matches_stem(&self, stem: &str) -> bool {
+        self.stem_re
+            .find(stem)
+            .is_some_and(|found| found.start() == 0 && found.end() == stem.len())
+    }
+
+    // whether a directory is a migration, it must be named as one and contain an `up.sql` script
+    fn matches_dir(&self, path: &Path) -> bool {
+        path.file_name()
+            .and_then(OsStr::to_str)
+            .is_some_and(|dir_name| self.matches_stem(dir_name))
+            && path.join(UP_SQL).is_file()
+    }
+}
+
+impl Default for NamingStrategy {
+    fn default() -> NamingStrategy {
+        default_naming_strategy().clone()
+    }
+}
+
+pub(crate) fn default_naming_strategy() -> &'static NamingStrategy {
+    static NAMING: OnceLock<NamingStrategy> = OnceLock::new();
+    NAMING.get_or_init(|| NamingStrategy::new(STEM_RE).unwrap())
+}
+
+fn dialect_suffix(dialect: ConfigDbType) -> &'static str {
+    match dialect {
+        ConfigDbType::Postgres => "postgres",
+        ConfigDbType::Mysql => "mysql",
+        ConfigDbType::Sqlite => "sqlite",
+        ConfigDbType::Mssql => "mssql",
     }
 }
 
 /// Parse a migration filename stem into a prefix, version, and name.
+/// For dot separated versions, i.e. `2.1.3`, the returned version is the major one.
 pub fn parse_migration_name(name: &str) -> Result<(Type, SchemaVersion, String), Error> {
-    let captures = file_stem_re()
-        .captures(name)
-        .filter(|caps| caps.len() == 4)
-        .ok_or_else(|| Error::new(Kind::InvalidName, None))?;
-    let version: SchemaVersion = captures[1]
-        .parse()
-        .map_err(|_| Error::new(Kind::InvalidVersion, None))?;
-
-    let name: String = (&captures[3]).into();
-    let prefix = match &captures[2] {
-        "V" => Type::Versioned,
-        "U" => Type::Unversioned,
-        "R" => Type::Rerunnable,
-        _ => unreachable!(),
-    };
-
-    Ok((prefix, version, name))
+    default_naming_strategy().parse(name)
 }
 
-/// find migrations on file system recursively across directories given a location and [MigrationType]
+/// find migrations on file system recursively across directories given a location and [MigrationType],
+/// directory migrations are returned as the path of their directory
 pub fn find_migration_files(
     location: impl AsRef<Path>,
     migration_type: MigrationType,
+) -> Result<impl Iterator<Item = PathBuf>, Error> {
+    find_migration_files_with_naming(location, migration_type, default_naming_strategy())
+}
+
+/// find migrations on file system recursively across directories given a location, [MigrationType] and [NamingStrategy],
+/// directory migrations are returned as the path of their directory
+pub fn find_migration_files_with_naming(
+    location: impl AsRef<Path>,
+    migration_type: MigrationType,
+    naming: &NamingStrategy,
 ) -> Result<impl Iterator<Item = PathBuf>, Error> {
     let location: &Path = location.as_ref();
     let location = location.canonicalize().map_err(|err| {
@@ -82,26 +281,48 @@ pub fn find_migration_files(
         )
     })?;
 
-    let re = migration_type.file_match_re();
+    let naming = naming.clone();
+    let parent_naming = naming.clone();
     let file_paths = WalkDir::new(location)
         .into_iter()
+        // the scripts and metadata of directory migrations are read when loading them,
+        // so don't descend into them
+        .filter_entry(move |entry| {
+            entry.depth() == 0
+                || !entry
+                    .path()
+                    .parent()
+                    .is_some_and(|parent| parent_naming.matches_dir(parent))
+        })
         .filter_map(Result::ok)
-        .filter(|p| !p.file_type().is_dir())
-        .map(DirEntry::into_path)
-        // filter by migration file regex
-        .filter(
-            move |entry| match entry.file_name().and_then(OsStr::to_str) {
-                Some(file_name) if re.is_match(file_name) => true,
-                Some(file_name) => {
+        .filter(move |entry| {
+            if entry.file_type().is_dir() {
+                return naming.matches_dir(entry.path());
+            }
+            // filter by migration naming strategy
+            match entry.file_name().to_str() {
+                Some(file_name) if naming.matches_file(file_name, &migration_type) => true,
+                Some(file_name) if naming.pattern() == STEM_RE => {
                     log::warn!(
-                        "File \"{}\" does not adhere to the migration naming convention. Migrations must be named in the format {{1}}[U|V|R]__{{2}}.sql or {{1}}[U|V|R]__{{2}}.rs, where {{1}} represents the migration version and {{2}} the name.",
+                        "File \"{}\" does not adhere to the migration naming convention. Migrations must be named in the format {{1}}[U|V|R|B]__{{2}}.sql or {{1}}[U|V|R|B]__{{2}}.rs, where {{1}} represents the migration version and {{2}} the name, optionally followed by a database dialect such as {{1}}[U|V|R|B]__{{2}}.postgres.sql, or be a {{1}}[U|V|R|B]__{{2}} directory containing an up.sql script.",
                         file_name
                     );
                     false
                 }
+                // with a custom pattern, other files commonly live next to the migrations,
+                // i.e. their down scripts or READMEs, so don't warn about them
+                Some(file_name) => {
+                    log::debug!(
+                        "File \"{}\" does not match the migration naming pattern \"{}\".",
+                        file_name,
+                        naming.pattern()
+                    );
+                    false
+                }
                 None => false,
-            },
-        );
+            }
+        })
+        .map(DirEntry::into_path);
 
     Ok(file_paths)
 }
@@ -109,20 +330,26 @@ pub fn find_migration_files(
 /// Loads SQL migrations from a path. This enables dynamic migration discovery, as opposed to
 /// embedding. The resulting collection is ordered by version.
 pub fn load_sql_migrations(location: impl AsRef<Path>) -> Result<Vec<Migration>, Error> {
-    let migration_files = find_migration_files(location, MigrationType::Sql)?;
+    load_sql_migrations_with_naming(location, default_naming_strategy())
+}
+
+/// Loads SQL migrations named according to a [NamingStrategy] from a path.
+/// The resulting collection is ordered by version.
+pub fn load_sql_migrations_with_naming(
+    location: impl AsRef<Path>,
+    naming: &NamingStrategy,
+) -> Result<Vec<Migration>, Error> {
+    let migration_files = find_migration_files_with_naming(location, MigrationType::Sql, naming)?;
 
     let mut migrations = vec![];
 
     for path in migration_files {
-        let sql = std::fs::read_to_string(path.as_path()).map_err(|e| {
-            let path = path.to_owned();
-            let kind = match e.kind() {
-                std::io::ErrorKind::NotFound => Kind::InvalidMigrationPath(path, e),
-                _ => Kind::InvalidMigrationFile(path, e),
-            };
+        if path.is_dir() {
+            migrations.push(load_migration_directory(&path, naming)?);
+            continue;
+        }
 
-            Error::new(kind, None)
-        })?;
+        let sql = read_migration_file(&path)?;
 
         //safe to call unwrap as find_migration_filenames returns canonical paths
         let filename = path
@@ -130,7 +357,7 @@ pub fn load_sql_migrations(location: impl AsRef<Path>) -> Result<Vec<Migration>,
             .and_then(|file| file.to_os_string().into_string().ok())
             .unwrap();
 
-        let migration = Migration::unapplied(&filename, &sql)?;
+        let migration = Migration::unapplied_with_naming(&filename, &sql, naming)?;
         migrations.push(migration);
     }
 
@@ -138,9 +365,81 @@ pub fn load_sql_migrations(location: impl AsRef<Path>) -> Result<Vec<Migration>,
     Ok(migrations)
 }
 
+/// Loads a directory migration, made of an `up.sql` script, an optional `down.sql` script
+/// and an optional `migration.toml` metadata file, the directory is named according to a [NamingStrategy].
+pub fn load_migration_directory(
+    location: impl AsRef<Path>,
+    naming: &NamingStrategy,
+) -> Result<Migration, Error> {
+    let location = location.as_ref();
+    let dir_name = location
+        .file_name()
+        .and_then(OsStr::to_str)
+        .ok_or_else(|| Error::new(Kind::InvalidName, None))?;
+
+    let sql = read_migration_file(&location.join(UP_SQL))?;
+
+    let down_path = location.join(DOWN_SQL);
+    let down_sql = if down_path.is_file() {
+        Some(read_migration_file(&down_path)?)
+    } else {
+        None
+    };
+
+    let metadata_path = location.join(METADATA_FILE);
+    let metadata = if metadata_path.is_file() {
+        parse_migration_metadata(&metadata_path)?
+    } else {
+        MigrationMetadata::default()
+    };
+
+    Ok(Migration::unapplied_with_naming(dir_name, &sql, naming)?
+        .set_down_sql(down_sql)
+        .set_metadata(metadata))
+}
+
+fn read_migration_file(path: &Path) -> Result<String, Error> {
+    std::fs::read_to_string(path).map_err(|e| {
+        let path = path.to_owned();
+        let kind = match e.kind() {
+            std::io::ErrorKind::NotFound => Kind::InvalidMigrationPath(path, e),
+            _ => Kind::InvalidMigrationFile(path, e),
+        };
+
+        Error::new(kind, None)
+    })
+}
+
+#[cfg(feature = "toml")]
+fn parse_migration_metadata(path: &Path) -> Result<MigrationMetadata, Error> {
+    let metadata = read_migration_file(path)?;
+    toml::from_str(&metadata).map_err(|err| {
+        Error::new(
+            Kind::InvalidMigrationMetadata(path.to_path_buf(), err.to_string()),
+            None,
+        )
+    })
+}
+
+#[cfg(not(feature = "toml"))]
+fn parse_migration_metadata(path: &Path) -> Result<MigrationMetadata, Error> {
+    Err(Error::new(
+        Kind::InvalidMigrationMetadata(
+            path.to_path_buf(),
+            "the toml feature is required to read migration metadata".into(),
+        ),
+        None,
+    ))
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{find_migration_files, load_sql_migrations, MigrationType};
+    use super::{
+        find_migration_files, find_migration_files_with_naming, load_sql_migrations,
+        load_sql_migrations_with_naming, parse_migration_name, MigrationType, NamingStrategy, Type,
+    };
+    use crate::config::ConfigDbType;
+    use crate::error::Kind;
     use std::fs;
     use std::path::PathBuf;
     use tempfile::TempDir;
@@ -230,6 +529,251 @@ mod tests {
         assert!(mods.next().is_none());
     }
 
+    #[test]
+    fn finds_dialect_migrations() {
+        let tmp_dir = TempDir::new().unwrap();
+        let migrations_dir = tmp_dir.path().join("migrations");
+        fs::create_dir(&migrations_dir).unwrap();
+        let sql1 = migrations_dir.join("1V__first.sql");
+        fs::File::create(&sql1).unwrap();
+        let sql2 = migrations_dir.join("1V__first.postgres.sql");
+        fs::File::create(&sql2).unwrap();
+        let sql3 = migrations_dir.join("1V__first.oracle.sql");
+        fs::File::create(sql3).unwrap();
+
+        let mut mods: Vec<PathBuf> = find_migration_files(migrations_dir, MigrationType::All)
+            .unwrap()
+            .collect();
+        mods.sort();
+        assert_eq!(2, mods.len());
+        assert_eq!(sql2.canonicalize().unwrap(), mods[0]);
+        assert_eq!(sql1.canonicalize().unwrap(), mods[1]);
+    }
+
+    #[test]
+    fn parses_migration_dialect() {
+        assert_eq!(
+            Some(ConfigDbType::Sqlite),
+            NamingStrategy::default()
+                .parse_parts("5V__add_index.sqlite")
+                .unwrap()
+                .dialect
+        );
+        assert_eq!(
+            None,
+            NamingStrategy::default()
+                .parse_parts("5V__add_index")
+                .unwrap()
+                .dialect
+        );
+
+        let (_, version, name) = parse_migration_name("5V__add_index.mssql").unwrap();
+        assert_eq!(5, version);
+        assert_eq!("add_index", name);
+    }
+
+    #[test]
+    fn parses_baseline_migration_name() {
+        let (prefix, version, name) = parse_migration_name("12B__baseline").unwrap();
+        assert_eq!(Type::Baseline, prefix);
+        assert_eq!(12, version);
+        assert_eq!("baseline", name);
+    }
+
+    #[test]
+    #[cfg(not(feature = "dotted-versions"))]
+    fn fails_to_parse_dotted_version() {
+        let err = parse_migration_name("1.2V__add_index").unwrap_err();
+        assert!(matches!(err.kind(), Kind::InvalidVersion));
+    }
+
+    #[test]
+    #[cfg(feature = "dotted-versions")]
+    fn parses_dotted_version() {
+        let (_, version, name) = parse_migration_name("2.1.3V__add_index").unwrap();
+        assert_eq!(2, version);
+        assert_eq!("add_index", name);
+        let naming = NamingStrategy::default();
+        assert_eq!(
+            vec![1, 3],
+            naming
+                .parse_parts("2.1.3V__add_index")
+                .unwrap()
+                .sub_versions
+        );
+        assert!(naming
+            .parse_parts("2V__add_index")
+            .unwrap()
+            .sub_versions
+            .is_empty());
+    }
+
+    #[test]
+    fn finds_migrations_with_naming_strategy() {
+        let tmp_dir = TempDir::new().unwrap();
+        let migrations_dir = tmp_dir.path().join("migrations");
+        fs::create_dir(&migrations_dir).unwrap();
+        let sql1 = migrations_dir.join("20240504090241_create_users.up.sql");
+        fs::File::create(&sql1).unwrap();
+        let sql2 = migrations_dir.join("20240504090241_create_users.down.sql");
+        fs::File::create(&sql2).unwrap();
+        let sql3 = migrations_dir.join("1V__first.sql");
+        fs::File::create(&sql3).unwrap();
+
+        let naming = NamingStrategy::new(r"^(?P<version>\d+)_(?P<name>\w+)\.up$").unwrap();
+        let mods: Vec<PathBuf> =
+            find_migration_files_with_naming(migrations_dir, MigrationType::All, &naming)
+                .unwrap()
+                .collect();
+        assert_eq!(1, mods.len());
+        assert_eq!(sql1.canonicalize().unwrap(), mods[0]);
+    }
+
+    #[test]
+    fn parses_timestamp_versions_only_with_int8_versions() {
+        let naming = NamingStrategy::new(r"^(?P<version>\d+)_(?P<name>\w+)\.up$").unwrap();
+        let parsed = naming.parse("20240504090241_create_users.up");
+        #[cfg(feature = "int8-versions")]
+        assert_eq!(20240504090241, parsed.unwrap().1);
+        #[cfg(not(feature = "int8-versions"))]
+        assert!(matches!(parsed.unwrap_err().kind(), Kind::InvalidVersion));
+    }
+
+    #[test]
+    fn normalizes_migration_name_with_naming_strategy() {
+        let naming =
+            NamingStrategy::new(r"^(?P<version>\d+)_(?P<prefix>[UVR])_(?P<name>\w+)$").unwrap();
+        assert_eq!(
+            "42U__create_users",
+            naming.canonical_name("42_U_create_users").unwrap()
+        );
+        assert_eq!(
+            "5V__add_index.postgres",
+            NamingStrategy::default()
+                .canonical_name("5V__add_index.postgres")
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn rejects_naming_strategy_without_version() {
+        let err = NamingStrategy::new(r"^(?P<name>\w+)$").unwrap_err();
+        assert!(matches!(err.kind(), Kind::InvalidNamingPattern(_)));
+    }
+
+    #[test]
+    fn loads_migrations_with_naming_strategy() {
+        let tmp_dir = TempDir::new().unwrap();
+        let migrations_dir = tmp_dir.path().join("migrations");
+        fs::create_dir(&migrations_dir).unwrap();
+        let sql = "CREATE TABLE users (id INTEGER PRIMARY KEY);";
+        fs::write(migrations_dir.join("2_create_users.up.sql"), sql).unwrap();
+        fs::write(migrations_dir.join("1_init.up.sql"), sql).unwrap();
+
+        let naming = NamingStrategy::new(r"^(?P<version>\d+)_(?P<name>\w+)\.up$").unwrap();
+        let migrations = load_sql_migrations_with_naming(migrations_dir, &naming).unwrap();
+        assert_eq!(2, migrations.len());
+        assert_eq!(1, migrations[0].version());
+        assert_eq!("init", migrations[0].name());
+        assert_eq!("create_users", migrations[1].name());
+        assert_eq!("2V__create_users", migrations[1].to_string());
+    }
+
+    #[test]
+    fn finds_directory_migrations() {
+        let tmp_dir = TempDir::new().unwrap();
+        let migrations_dir = tmp_dir.path().join("migrations");
+        fs::create_dir(&migrations_dir).unwrap();
+        let sql1 = migrations_dir.join("1V__first.sql");
+        fs::File::create(&sql1).unwrap();
+        let dir2 = migrations_dir.join("2V__split_users");
+        fs::create_dir(&dir2).unwrap();
+        fs::File::create(dir2.join("up.sql")).unwrap();
+        fs::File::create(dir2.join("down.sql")).unwrap();
+        fs::File::create(dir2.join("migration.toml")).unwrap();
+        // nested within a directory migration, which is loaded as a whole
+        let nested = dir2.join("4V__nested");
+        fs::create_dir(&nested).unwrap();
+        fs::File::create(nested.join("up.sql")).unwrap();
+        fs::File::create(nested.join("5V__nested.sql")).unwrap();
+        // not a migration without an up.sql script
+        let dir3 = migrations_dir.join("3V__empty");
+        fs::create_dir(&dir3).unwrap();
+
+        let mut mods: Vec<PathBuf> = find_migration_files(migrations_dir, MigrationType::Sql)
+            .unwrap()
+            .collect();
+        mods.sort();
+        assert_eq!(2, mods.len());
+        assert_eq!(sql1.canonicalize().unwrap(), mods[0]);
+        assert_eq!(dir2.canonicalize().unwrap(), mods[1]);
+    }
+
+    #[test]
+    #[cfg(feature = "toml")]
+    fn loads_directory_migrations() {
+        let tmp_dir = TempDir::new().unwrap();
+        let migrations_dir = tmp_dir.path().join("migrations");
+        fs::create_dir(&migrations_dir).unwrap();
+        let dir = migrations_dir.join("7V__split_users");
+        fs::create_dir(&dir).unwrap();
+        fs::write(
+            dir.join("up.sql"),
+            "ALTER TABLE users ADD COLUMN email TEXT;",
+        )
+        .unwrap();
+        fs::write(dir.join("down.sql"), "ALTER TABLE users DROP COLUMN email;").unwrap();
+        fs::write(
+            dir.join("migration.toml"),
+            r#"
+            description = "split users"
+            transactional = false
+            author = "jane"
+            depends_on = ["5V__users"]
+            "#,
+        )
+        .unwrap();
+        fs::write(
+            migrations_dir.join("5V__users.sql"),
+            "CREATE TABLE users (id INTEGER PRIMARY KEY);",
+        )
+        .unwrap();
+
+        let migrations = load_sql_migrations(migrations_dir).unwrap();
+        assert_eq!(2, migrations.len());
+        let migration = &migrations[1];
+        assert_eq!(7, migration.version());
+        assert_eq!("split_users", migration.name());
+        assert_eq!(
+            Some("ALTER TABLE users ADD COLUMN email TEXT;"),
+            migration.sql()
+        );
+        assert_eq!(
+            Some("ALTER TABLE users DROP COLUMN email;"),
+            migration.down_sql()
+        );
+        let metadata = migration.metadata();
+        assert_eq!(Some("split users"), metadata.description.as_deref());
+        assert!(!metadata.transactional);
+        assert_eq!(None, metadata.context);
+        assert_eq!(Some("jane"), metadata.author.as_deref());
+        assert_eq!(vec!["5V__users".to_string()], metadata.depends_on);
+        assert!(migrations[0].metadata().transactional);
+    }
+
+    #[test]
+    #[cfg(feature = "toml")]
+    fn fails_to_load_directory_migration_with_invalid_metadata() {
+        let tmp_dir = TempDir::new().unwrap();
+        let dir = tmp_dir.path().join("7V__split_users");
+        fs::create_dir(&dir).unwrap();
+        fs::File::create(dir.join("up.sql")).unwrap();
+        fs::write(dir.join("migration.toml"), "dependencies = [\"5V__users\"]").unwrap();
+
+        let err = super::load_migration_directory(&dir, &NamingStrategy::default()).unwrap_err();
+        assert!(matches!(err.kind(), Kind::InvalidMigrationMetadata(..)));
+    }
+
     #[test]
     fn loads_migrations_from_path() {
         let tmp_dir = TempDir::new().unwrap();
@@ -7,10 +7,14 @@ use proc_macro::TokenStream;
 use proc_macro2::{Span as Span2, TokenStream as TokenStream2};
 use quote::quote;
 use quote::ToTokens;
-use refinery_core::{find_migration_files, MigrationType};
+use refinery_core::{
+    find_migration_files_with_naming, load_migration_directory, MigrationMetadata, MigrationType,
+    NamingStrategy,
+};
 use std::path::PathBuf;
 use std::{env, fs};
-use syn::{parse_macro_input, Ident, LitStr};
+use syn::parse::{Parse, ParseStream};
+use syn::{parse_macro_input, Ident, LitStr, Token};
 
 pub(crate) fn crate_root() -> PathBuf {
     let crate_root = env::var("CARGO_MANIFEST_DIR")
@@ -18,7 +22,38 @@ pub(crate) fn crate_root() -> PathBuf {
     PathBuf::from(crate_root)
 }
 
-fn migration_fn_quoted<T: ToTokens>(_migrations: Vec<T>) -> TokenStream2 {
+// arguments of `embed_migrations!`, an optional location followed by an optional `naming = "pattern"`
+struct EmbedArgs {
+    location: Option<LitStr>,
+    naming: Option<LitStr>,
+}
+
+impl Parse for EmbedArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let location = if input.peek(LitStr) {
+            Some(input.parse()?)
+        } else {
+            None
+        };
+
+        let mut naming = None;
+        if location.is_some() && !input.is_empty() {
+            input.parse::<Token![,]>()?;
+        }
+        if !input.is_empty() {
+            let key: Ident = input.parse()?;
+            if key != "naming" {
+                return Err(syn::Error::new(key.span(), "expected `naming`"));
+            }
+            input.parse::<Token![=]>()?;
+            naming = Some(input.parse()?);
+        }
+
+        Ok(EmbedArgs { location, naming })
+    }
+}
+
+fn migration_fn_quoted<T: ToTokens>(_migrations: Vec<T>, _dir_migrations: Vec<T>) -> TokenStream2 {
     let result = quote! {
         use refinery::{Migration, Runner, SchemaVersion};
         pub fn runner() -> Runner {
@@ -27,46 +62,111 @@ fn migration_fn_quoted<T: ToTokens>(_migrations: Vec<T>) -> TokenStream2 {
             for module in quoted_migrations.into_iter() {
                 migrations.push(Migration::unapplied(module.0, &module.1).unwrap());
             }
+            #(migrations.push(#_dir_migrations);)*
             Runner::new(&migrations)
         }
     };
     result
 }
 
+fn option_quoted(value: &Option<String>) -> TokenStream2 {
+    match value {
+        Some(value) => quote! { Some(#value.to_string()) },
+        None => quote! { None },
+    }
+}
+
+fn metadata_quoted(metadata: &MigrationMetadata) -> TokenStream2 {
+    let description = option_quoted(&metadata.description);
+    let transactional = metadata.transactional;
+    let context = option_quoted(&metadata.context);
+    let author = option_quoted(&metadata.author);
+    let depends_on = &metadata.depends_on;
+    quote! {
+        refinery::MigrationMetadata {
+            description: #description,
+            transactional: #transactional,
+            context: #context,
+            author: #author,
+            depends_on: vec![#(#depends_on.to_string()),*],
+        }
+    }
+}
+
 #[cfg(feature = "enums")]
 fn migration_enum_quoted(migration_names: &[impl AsRef<str>]) -> TokenStream2 {
-    use refinery_core::SchemaVersion;
+    use refinery_core::{Migration, SchemaVersion};
 
     let mut variants = Vec::new();
     let mut discriminants = Vec::new();
+    let mut seen = Vec::new();
 
     for m in migration_names {
         let m = m.as_ref();
-        let (_, version, name) = refinery_core::parse_migration_name(m)
+        let migration = Migration::unapplied(m, "")
             .unwrap_or_else(|e| panic!("Couldn't parse migration filename '{}': {:?}", m, e));
-        let version: SchemaVersion = version;
-        let variant = Ident::new(name.to_upper_camel_case().as_str(), Span2::call_site());
-        variants.push(quote! { #variant(Migration) = #version });
-        discriminants.push(quote! { #version => Self::#variant(migration) });
-    }
-    discriminants.push(quote! { v => panic!("Invalid migration version '{}'", v) });
-
-    #[cfg(feature = "int8-versions")]
-    let embedded = quote! {
-        #[repr(i64)]
-        #[derive(Debug)]
-        pub enum EmbeddedMigration {
-            #(#variants),*
+        let version: SchemaVersion = migration.version();
+        let sub_versions = migration.sub_versions().to_vec();
+        // dialect specific variants of a migration share its version and name
+        let key = (version, sub_versions.clone(), migration.name().to_string());
+        if seen.contains(&key) {
+            continue;
+        }
+        seen.push(key);
+        let variant = Ident::new(
+            migration.name().to_upper_camel_case().as_str(),
+            Span2::call_site(),
+        );
+        if cfg!(feature = "dotted-versions") {
+            // a dotted version doesn't fit in a discriminant, so it's matched on all of its parts instead
+            variants.push(quote! { #variant(Migration) });
+            discriminants
+                .push(quote! { (#version, [#(#sub_versions),*]) => Self::#variant(migration) });
+        } else {
+            variants.push(quote! { #variant(Migration) = #version });
+            discriminants.push(quote! { #version => Self::#variant(migration) });
         }
+    }
+
+    #[cfg(feature = "dotted-versions")]
+    let (embedded, from) = {
+        discriminants.push(quote! { (v, _) => panic!("Invalid migration version '{}'", v) });
+        (
+            quote! {
+                #[derive(Debug)]
+                pub enum EmbeddedMigration {
+                    #(#variants),*
+                }
+            },
+            quote! {
+                match (migration.version() as SchemaVersion, migration.sub_versions()) {
+                    #(#discriminants),*
+                }
+            },
+        )
     };
 
-    #[cfg(not(feature = "int8-versions"))]
-    let embedded = quote! {
-        #[repr(i32)]
-        #[derive(Debug)]
-        pub enum EmbeddedMigration {
-            #(#variants),*
-        }
+    #[cfg(not(feature = "dotted-versions"))]
+    let (embedded, from) = {
+        discriminants.push(quote! { v => panic!("Invalid migration version '{}'", v) });
+        #[cfg(feature = "int8-versions")]
+        let repr = quote! { #[repr(i64)] };
+        #[cfg(not(feature = "int8-versions"))]
+        let repr = quote! { #[repr(i32)] };
+        (
+            quote! {
+                #repr
+                #[derive(Debug)]
+                pub enum EmbeddedMigration {
+                    #(#variants),*
+                }
+            },
+            quote! {
+                match migration.version() as SchemaVersion {
+                    #(#discriminants),*
+                }
+            },
+        )
     };
 
     quote! {
@@ -75,9 +175,7 @@ fn migration_enum_quoted(migration_names: &[impl AsRef<str>]) -> TokenStream2 {
 
         impl From<Migration> for EmbeddedMigration {
             fn from(migration: Migration) -> Self {
-                match migration.version() as SchemaVersion {
-                    #(#discriminants),*
-                }
+                #from
             }
         }
     }
@@ -90,32 +188,89 @@ fn migration_enum_quoted(migration_names: &[impl AsRef<str>]) -> TokenStream2 {
 ///
 /// To be a valid migration module, it has to be named in the format `{1}(U|V|R)__{2}.{3} ` where `{1}` represents the migration version and `{2}` the name and `{3} is "rs" or "sql".
 /// For the name alphanumeric characters plus "_" are supported.
+/// A migration can also be a `{1}(U|V|R)__{2}` directory containing an `up.sql` script, an optional `down.sql` script
+/// and an optional `migration.toml` file with its [`MigrationMetadata`].
+/// The name can be followed by a database dialect, `postgres`, `mysql`, `sqlite` or `mssql`, i.e. `{1}(U|V|R)__{2}.postgres.{3}`,
+/// that variant then replaces the generic migration with the same version and name when running on that database.
+/// Migrations named otherwise can be embedded by passing a [`NamingStrategy`] pattern, i.e.
+/// `embed_migrations!("./migrations", naming = r"^(?P<version>\d+)_(?P<name>\w+)\.up$")`,
+/// their names are then converted to the default format at compile time.
 /// The Rust migration file must have a function named `migration()` that returns a [`std::string::String`].
 /// The SQL migration file must have valid sql instructions for the database you want it to run on.
 ///
 /// [`Runner`]: https://docs.rs/refinery/latest/refinery/struct.Runner.html
+/// [`NamingStrategy`]: https://docs.rs/refinery/latest/refinery/struct.NamingStrategy.html
+/// [`MigrationMetadata`]: https://docs.rs/refinery/latest/refinery/struct.MigrationMetadata.html
 #[proc_macro]
 pub fn embed_migrations(input: TokenStream) -> TokenStream {
-    let location = if input.is_empty() {
-        crate_root().join("migrations")
-    } else {
-        let location: LitStr = parse_macro_input!(input);
-        crate_root().join(location.value())
+    let args = parse_macro_input!(input as EmbedArgs);
+    let location = match args.location {
+        Some(location) => crate_root().join(location.value()),
+        None => crate_root().join("migrations"),
+    };
+    let naming = match args.naming {
+        Some(pattern) => NamingStrategy::new(&pattern.value())
+            .unwrap_or_else(|e| panic!("Invalid migration naming pattern: {}", e)),
+        None => NamingStrategy::default(),
     };
 
-    let migration_files =
-        find_migration_files(location, MigrationType::All).expect("error getting migration files");
+    let migration_files = find_migration_files_with_naming(location, MigrationType::All, &naming)
+        .expect("error getting migration files");
 
     let mut migrations_mods = Vec::new();
     let mut _migrations = Vec::new();
+    let mut _dir_migrations = Vec::new();
     let mut migration_filenames = Vec::new();
 
     for migration in migration_files {
+        if migration.is_dir() {
+            let dir_migration = load_migration_directory(&migration, &naming).unwrap_or_else(|e| {
+                panic!("Couldn't load migration '{}': {:?}", migration.display(), e)
+            });
+            // safe to call unwrap as find_migration_filenames returns canonical paths
+            let dir_name = migration.file_name().and_then(|dir| dir.to_str()).unwrap();
+            let filename = naming.canonical_name(dir_name).unwrap_or_else(|e| {
+                panic!(
+                    "Couldn't parse migration directory name '{}': {:?}",
+                    dir_name, e
+                )
+            });
+            migration_filenames.push(filename.clone());
+
+            let up_path = migration.join("up.sql").display().to_string();
+            let down_sql = match dir_migration.down_sql() {
+                Some(_) => {
+                    let down_path = migration.join("down.sql").display().to_string();
+                    quote! { Some(include_str!(#down_path).to_string()) }
+                }
+                None => quote! { None },
+            };
+            let metadata = metadata_quoted(dir_migration.metadata());
+            // also include the metadata file so we trigger recompilation if it changes
+            let recompile = if migration.join("migration.toml").is_file() {
+                let metadata_path = migration.join("migration.toml").display().to_string();
+                quote! { const _RECOMPILE_IF_CHANGED: &str = include_str!(#metadata_path); }
+            } else {
+                quote!()
+            };
+            _dir_migrations.push(quote! {{
+                #recompile
+                Migration::unapplied(#filename, include_str!(#up_path))
+                    .unwrap()
+                    .set_down_sql(#down_sql)
+                    .set_metadata(#metadata)
+            }});
+            continue;
+        }
+
         // safe to call unwrap as find_migration_filenames returns canonical paths
-        let filename = migration
+        let file_stem = migration
             .file_stem()
             .and_then(|file| file.to_os_string().into_string().ok())
             .unwrap();
+        let filename = naming.canonical_name(&file_stem).unwrap_or_else(|e| {
+            panic!("Couldn't parse migration filename '{}': {:?}", file_stem, e)
+        });
         let path = migration.display().to_string();
         let extension = migration.extension().unwrap();
         migration_filenames.push(filename.clone());
@@ -127,7 +282,12 @@ pub fn embed_migrations(input: TokenStream) -> TokenStream {
                 .unwrap()
                 .parse::<TokenStream2>()
                 .unwrap();
-            let ident = Ident::new(&filename, Span2::call_site());
+            // names starting with the version, and dialect specific variants, i.e. `5V__add_index.postgres`,
+            // aren't valid identifiers
+            let ident = Ident::new(
+                &format!("_{}", filename.replace('.', "_")),
+                Span2::call_site(),
+            );
             let mig_mod = quote! {pub mod #ident {
                 #rs_content
                 // also include the file as str so we trigger recompilation if it changes
@@ -138,7 +298,7 @@ pub fn embed_migrations(input: TokenStream) -> TokenStream {
         }
     }
 
-    let fnq = migration_fn_quoted(_migrations);
+    let fnq = migration_fn_quoted(_migrations, _dir_migrations);
     #[cfg(feature = "enums")]
     let enums = migration_enum_quoted(migration_filenames.as_slice());
     #[cfg(not(feature = "enums"))]
@@ -156,9 +316,13 @@ pub fn embed_migrations(input: TokenStream) -> TokenStream {
 
 #[cfg(test)]
 mod tests {
-    use super::{migration_fn_quoted, quote};
+    use super::{migration_fn_quoted, quote, EmbedArgs};
 
-    #[cfg(all(feature = "enums", feature = "int8-versions"))]
+    #[cfg(all(
+        feature = "enums",
+        feature = "int8-versions",
+        not(feature = "dotted-versions")
+    ))]
     #[test]
     fn test_enum_fn_i8() {
         let expected = concat! {
@@ -180,7 +344,11 @@ mod tests {
         assert_eq!(expected, enums);
     }
 
-    #[cfg(all(feature = "enums", not(feature = "int8-versions")))]
+    #[cfg(all(
+        feature = "enums",
+        not(feature = "int8-versions"),
+        not(feature = "dotted-versions")
+    ))]
     #[test]
     fn test_enum_fn() {
         let expected = concat! {
@@ -202,6 +370,39 @@ mod tests {
         assert_eq!(expected, enums);
     }
 
+    #[cfg(all(
+        feature = "enums",
+        not(feature = "int8-versions"),
+        feature = "dotted-versions"
+    ))]
+    #[test]
+    fn test_enum_fn_dotted() {
+        let expected = concat! {
+            "# [derive (Debug)] ",
+            "pub enum EmbeddedMigration { ",
+            "Foo (Migration) , ",
+            "Bar (Migration) , ",
+            "Baz (Migration) ",
+            "} ",
+            "impl From < Migration > for EmbeddedMigration { ",
+            "fn from (migration : Migration) -> Self { ",
+            "match (migration . version () as SchemaVersion , migration . sub_versions ()) { ",
+            "(2i32 , []) => Self :: Foo (migration) , ",
+            "(2i32 , [1i32]) => Self :: Bar (migration) , ",
+            "(2i32 , [2i32]) => Self :: Baz (migration) , ",
+            "(v , _) => panic ! (\"Invalid migration version '{}'\" , v) ",
+            "} } }"
+        };
+        let enums = super::migration_enum_quoted(&[
+            "2V__foo",
+            "2.1V__bar",
+            "2.2V__baz",
+            "2.2V__baz.postgres",
+        ])
+        .to_string();
+        assert_eq!(expected, enums);
+    }
+
     #[test]
     fn test_quote_fn() {
         let migs = vec![quote!("1V__first", "valid_sql_file")];
@@ -215,6 +416,22 @@ mod tests {
             "} ",
             "Runner :: new (& migrations) }"
         };
-        assert_eq!(expected, migration_fn_quoted(migs).to_string());
+        assert_eq!(expected, migration_fn_quoted(migs, vec![]).to_string());
+    }
+
+    #[test]
+    fn test_parse_embed_args() {
+        let args: EmbedArgs =
+            syn::parse_str(r#""./migrations", naming = r"^(?P<version>\d+)_(?P<name>\w+)$""#)
+                .unwrap();
+        assert_eq!("./migrations", args.location.unwrap().value());
+        assert_eq!(
+            r"^(?P<version>\d+)_(?P<name>\w+)$",
+            args.naming.unwrap().value()
+        );
+
+        let args: EmbedArgs = syn::parse_str("").unwrap();
+        assert!(args.location.is_none() && args.naming.is_none());
+        assert!(syn::parse_str::<EmbedArgs>(r#""./migrations", location = "x""#).is_err());
     }
 }